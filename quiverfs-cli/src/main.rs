@@ -1,13 +1,11 @@
 use clap::Parser;
-use quiverfs_core::common_schema::{
-    ArrowSchemaInspector, ParquetSchemaInspector, SchemaInspectable,
-};
-use quiverfs_core::file_discovery::discover_data_files;
+use quiverfs_core::Config;
+use quiverfs_fuse::MountArgs;
 use std::path::PathBuf;
 
 #[derive(Parser)]
 #[command(name = "featherfs")]
-#[command(about = "Inspect Arrow and Parquet file schemas in a directory", long_about = None)]
+#[command(about = "Inspect and mount directories of Arrow and Parquet files", long_about = None)]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
@@ -19,6 +17,16 @@ enum Commands {
     Inspect {
         /// Directory to scan
         dir: PathBuf,
+        /// TOML file declaring tables, as passed to `mount --config`
+        #[arg(long, value_name = "PATH")]
+        config: Option<PathBuf>,
+    },
+    /// Mount a directory of Arrow/Parquet files as a filesystem
+    Mount(MountArgs),
+    /// Unmount a filesystem mounted with `mount`
+    Umount {
+        /// Directory the filesystem is mounted on
+        mountpoint: PathBuf,
     },
 }
 
@@ -26,31 +34,59 @@ fn main() {
     let cli = Cli::parse();
 
     match &cli.command {
-        Commands::Inspect { dir } => {
-            let files = discover_data_files(dir);
-            if files.is_empty() {
+        Commands::Inspect { dir, config } => {
+            let config = match config {
+                Some(path) => Config::load(path).unwrap_or_else(|e| {
+                    eprintln!("Error: {e}");
+                    std::process::exit(1);
+                }),
+                None => Config::default(),
+            };
+            // The same tables as the ones shown when `dir` is mounted
+            let dir = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+            let datasets = config.datasets(&dir);
+            if datasets.is_empty() {
                 eprintln!("No Arrow or Parquet files found in {:?}", dir);
                 std::process::exit(1);
             }
-            for file in files {
-                let path = file.to_string_lossy();
-                let res = if path.ends_with(".arrow") {
-                    ArrowSchemaInspector::inspect_schema(&file).map(|schema| format!("{schema:#?}"))
-                } else if path.ends_with(".parquet") {
-                    ParquetSchemaInspector::inspect_schema(&file)
-                        .map(|schema| format!("{schema:#?}"))
+            for dataset in datasets {
+                // Declared tables are reported by name, multi-part datasets by directory and
+                // single files by path
+                let header = if dataset.view.is_some() {
+                    format!("Table: {} ({} parts)", dataset.name, dataset.parts.len())
+                } else if dataset.root.is_file() {
+                    format!("File: {}", dataset.root.to_string_lossy())
                 } else {
-                    continue;
+                    format!(
+                        "Dataset: {} ({} parts)",
+                        dataset.root.to_string_lossy(),
+                        dataset.parts.len()
+                    )
                 };
-                match res {
+                for diagnostic in &dataset.diagnostics {
+                    eprintln!("{header}\nWarning: {diagnostic}\n");
+                }
+                match dataset.table_schema() {
                     Ok(schema) => {
-                        println!("File: {path}\nSchema:\n{schema}\n");
+                        println!("{header}\nSchema:\n{schema:#?}\n");
                     }
                     Err(e) => {
-                        eprintln!("File: {path}\nError: {e}\n");
+                        eprintln!("{header}\nError: {e}\n");
                     }
                 }
             }
         }
+        Commands::Mount(args) => {
+            if let Err(e) = quiverfs_fuse::mount(args) {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
+        Commands::Umount { mountpoint } => {
+            if let Err(e) = quiverfs_fuse::unmount(mountpoint) {
+                eprintln!("Error: {e}");
+                std::process::exit(1);
+            }
+        }
     }
 }
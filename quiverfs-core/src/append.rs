@@ -0,0 +1,277 @@
+use crate::common_schema::FileFormat;
+use crate::dataset::Dataset;
+use crate::ingest::{TextFormat, write_arrow, write_parquet_with};
+use crate::table_reader::read_record_batches;
+use arrow::csv::reader::Format;
+use arrow::datatypes::SchemaRef;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ARROW_SCHEMA_META_KEY;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::file::properties::{WriterProperties, WriterVersion};
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::{Path, PathBuf};
+
+/// Parses rows written to a table's append file using the table's schema, so that values of
+/// the wrong type, missing values in non-nullable columns and unknown JSON keys are rejected.
+///
+/// CSV input may start with a header row naming every column in order, with the names
+/// optionally quoted. Otherwise each record is a row.
+pub fn parse_rows(
+    data: &[u8],
+    format: TextFormat,
+    schema: &SchemaRef,
+) -> Result<Vec<RecordBatch>, String> {
+    match format {
+        TextFormat::Csv => {
+            // The first record is a header if its fields are the column names, which may be
+            // quoted or followed by spaces
+            let first = Format::default()
+                .with_header(true)
+                .infer_schema(Cursor::new(data), Some(0));
+            let has_header = first.is_ok_and(|(header, _)| {
+                header.fields().len() == schema.fields().len()
+                    && header
+                        .fields()
+                        .iter()
+                        .zip(schema.fields())
+                        .all(|(name, field)| name.name().trim() == field.name())
+            });
+            let reader = arrow::csv::ReaderBuilder::new(schema.clone())
+                .with_format(Format::default().with_header(has_header))
+                .build(Cursor::new(data))
+                .map_err(|e| format!("Failed to read CSV: {}", e))?;
+            reader
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read CSV: {}", e))
+        }
+        TextFormat::Jsonl => {
+            let reader = arrow::json::ReaderBuilder::new(schema.clone())
+                .with_strict_mode(true)
+                .build(BufReader::new(data))
+                .map_err(|e| format!("Failed to read JSON: {}", e))?;
+            reader
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read JSON: {}", e))
+        }
+    }
+}
+
+/// The largest single-file table that rows are appended to, as it is rewritten in full.
+const MAX_REWRITE_SIZE: u64 = 256 << 20;
+
+/// Picks an unused `part-NNNNN` name in a dataset directory.
+fn next_part_path(dataset: &Dataset, format: &FileFormat) -> PathBuf {
+    let extension = match format {
+        FileFormat::Arrow => "arrow",
+        FileFormat::Parquet => "parquet",
+    };
+    (dataset.parts.len()..)
+        .map(|n| dataset.root.join(format!("part-{:05}.{}", n, extension)))
+        .find(|path| !path.exists())
+        .unwrap_or_default()
+}
+
+/// Reads the writer settings of a Parquet file from its footer: the compression of each
+/// column, the row group size and the key-value metadata.
+fn parquet_properties(path: &Path) -> Result<WriterProperties, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+        .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+    let metadata = builder.metadata();
+    let mut properties = WriterProperties::builder();
+    if metadata.file_metadata().version() >= 2 {
+        properties = properties.set_writer_version(WriterVersion::PARQUET_2_0);
+    }
+    // The Arrow schema is written again from the table's schema
+    if let Some(pairs) = metadata.file_metadata().key_value_metadata() {
+        let pairs = pairs
+            .iter()
+            .filter(|pair| pair.key != ARROW_SCHEMA_META_KEY)
+            .cloned()
+            .collect();
+        properties = properties.set_key_value_metadata(Some(pairs));
+    }
+    // Every row group but the last is full
+    let rows = metadata.row_groups().iter().map(|group| group.num_rows());
+    if let Some(max_rows) = rows.max().filter(|rows| *rows > 0) {
+        properties = properties.set_max_row_group_size(max_rows as usize);
+    }
+    if let Some(group) = metadata.row_groups().first() {
+        for column in group.columns() {
+            properties = properties
+                .set_column_compression(column.column_path().clone(), column.compression());
+        }
+    }
+    Ok(properties.build())
+}
+
+/// Adds rows to a dataset, returning the file that was written.
+///
+/// Datasets made of part files get a new part in the format of their first part. Single-file
+/// tables are rewritten with the rows added as a new record batch, keeping their format, so
+/// they are read in full and appending to files larger than 256 MiB is refused. Parquet files
+/// keep the compression, row group size and key-value metadata of the first part.
+pub fn append_rows(dataset: &Dataset, batches: &[RecordBatch]) -> Result<PathBuf, String> {
+    let schema = dataset
+        .schema
+        .clone()
+        .ok_or_else(|| format!("Dataset '{}' has no readable parts", dataset.name))?;
+    let format = dataset.table_schema()?.format;
+
+    let (dest, batches) = if dataset.root.is_dir() {
+        (next_part_path(dataset, &format), batches.to_vec())
+    } else {
+        let size = dataset
+            .root
+            .metadata()
+            .map_err(|e| format!("Failed to open file: {}", e))?
+            .len();
+        if size > MAX_REWRITE_SIZE {
+            return Err(format!(
+                "{} is larger than {} MiB and would have to be rewritten, store the table as a \
+                 directory of part files to append to it",
+                dataset.root.display(),
+                MAX_REWRITE_SIZE >> 20
+            ));
+        }
+        let (_, mut existing) = read_record_batches(&dataset.root)?;
+        existing.extend_from_slice(batches);
+        (dataset.root.clone(), existing)
+    };
+    match format {
+        FileFormat::Arrow => write_arrow(&dest, schema, &batches)?,
+        FileFormat::Parquet => {
+            let first = dataset.parts.first().unwrap_or(&dataset.root);
+            write_parquet_with(&dest, schema, &batches, parquet_properties(first)?)?
+        }
+    }
+    Ok(dest)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::write_parquet;
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use parquet::basic::{Compression, ZstdLevel};
+    use parquet::format::KeyValue;
+    use std::sync::Arc;
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("country", DataType::Utf8, true),
+        ]))
+    }
+
+    fn rows(batches: &[RecordBatch]) -> usize {
+        batches.iter().map(|b| b.num_rows()).sum()
+    }
+
+    #[test]
+    fn test_parse_rows() {
+        let schema = schema();
+        let with_header = parse_rows(b"id,country\n1,DE\n2,\n", TextFormat::Csv, &schema).unwrap();
+        assert_eq!(rows(&with_header), 2);
+        let without_header = parse_rows(b"3,FR\n", TextFormat::Csv, &schema).unwrap();
+        assert_eq!(rows(&without_header), 1);
+        for header in ["\"id\",\"country\"\r\n", "id, country\n"] {
+            let data = format!("{header}5,ES\n");
+            let rows_read = parse_rows(data.as_bytes(), TextFormat::Csv, &schema).unwrap();
+            assert_eq!(rows(&rows_read), 1, "{header}");
+        }
+        let json = parse_rows(
+            b"{\"id\": 4, \"country\": \"IT\"}\n",
+            TextFormat::Jsonl,
+            &schema,
+        );
+        assert_eq!(rows(&json.unwrap()), 1);
+
+        assert!(parse_rows(b"x,DE\n", TextFormat::Csv, &schema).is_err());
+        assert!(parse_rows(b",DE\n", TextFormat::Csv, &schema).is_err());
+        assert!(parse_rows(b"1,DE,extra\n", TextFormat::Csv, &schema).is_err());
+        assert!(
+            parse_rows(
+                b"{\"id\": 1, \"city\": \"Rome\"}\n",
+                TextFormat::Jsonl,
+                &schema
+            )
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn test_append_rows_to_single_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.arrow");
+        let batch = RecordBatch::try_new(
+            schema(),
+            vec![
+                Arc::new(Int32Array::from(vec![1])),
+                Arc::new(StringArray::from(vec!["DE"])),
+            ],
+        )
+        .unwrap();
+        write_arrow(&path, schema(), &[batch]).unwrap();
+
+        let dataset = Dataset::from_parts("t".to_string(), path.clone(), vec![path.clone()]);
+        let new_rows = parse_rows(b"2,FR\n", TextFormat::Csv, &schema()).unwrap();
+        assert_eq!(append_rows(&dataset, &new_rows).unwrap(), path);
+
+        let (_, batches) = read_record_batches(&path).unwrap();
+        assert_eq!(batches.len(), 2);
+        assert_eq!(rows(&batches), 2);
+    }
+
+    #[test]
+    fn test_append_rows_keeps_parquet_properties() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.parquet");
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .set_max_row_group_size(2)
+            .set_key_value_metadata(Some(vec![KeyValue::new(
+                "origin".to_string(),
+                "export".to_string(),
+            )]))
+            .build();
+        let old_rows = parse_rows(b"1,DE\n2,FR\n3,IT\n", TextFormat::Csv, &schema()).unwrap();
+        write_parquet_with(&path, schema(), &old_rows, properties).unwrap();
+
+        let dataset = Dataset::from_parts("t".to_string(), path.clone(), vec![path.clone()]);
+        let new_rows = parse_rows(b"4,AT\n", TextFormat::Csv, &schema()).unwrap();
+        append_rows(&dataset, &new_rows).unwrap();
+
+        let file = File::open(&path).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let metadata = builder.metadata();
+        let row_groups: Vec<i64> = metadata.row_groups().iter().map(|g| g.num_rows()).collect();
+        assert_eq!(row_groups, [2, 2]);
+        let compression = metadata.row_group(1).column(0).compression();
+        assert!(matches!(compression, Compression::ZSTD(_)));
+        let pairs = metadata.file_metadata().key_value_metadata().unwrap();
+        assert!(pairs.iter().any(|pair| pair.key == "origin"));
+        assert_eq!(
+            pairs
+                .iter()
+                .filter(|pair| pair.key == ARROW_SCHEMA_META_KEY)
+                .count(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_append_rows_adds_part() {
+        let dir = tempfile::tempdir().unwrap();
+        let part = dir.path().join("part-00000.parquet");
+        let new_rows = parse_rows(b"1,DE\n", TextFormat::Csv, &schema()).unwrap();
+        write_parquet(&part, schema(), &new_rows).unwrap();
+
+        let dataset = Dataset::from_parts("t".to_string(), dir.path().to_path_buf(), vec![part]);
+        let written = append_rows(&dataset, &new_rows).unwrap();
+        assert!(written.ends_with("part-00001.parquet"));
+        assert_eq!(crate::dataset::dataset_parts(dir.path()).len(), 2);
+    }
+}
@@ -1,3 +1,4 @@
+use arrow::datatypes::Schema;
 use arrow::ipc::reader::FileReader;
 use serde_json::json;
 use std::fs::File;
@@ -9,8 +10,11 @@ pub fn arrow_schema_to_json<P: AsRef<Path>>(path: P) -> Result<serde_json::Value
     let reader = FileReader::try_new(BufReader::new(file), None)
         .map_err(|e| format!("Failed to read Arrow IPC file: {}", e))?;
 
-    let schema = reader.schema();
-    // Convert Arrow Schema to JSON
+    Ok(schema_to_json(&reader.schema()))
+}
+
+/// Describes an Arrow schema as JSON, in the format of [`arrow_schema_to_json`].
+pub fn schema_to_json(schema: &Schema) -> serde_json::Value {
     let fields_json: Vec<_> = schema
         .fields()
         .iter()
@@ -24,10 +28,10 @@ pub fn arrow_schema_to_json<P: AsRef<Path>>(path: P) -> Result<serde_json::Value
         })
         .collect();
 
-    Ok(json!({
+    json!({
         "fields": fields_json,
         "metadata": schema.metadata(),
-    }))
+    })
 }
 
 #[cfg(test)]
@@ -81,9 +85,11 @@ mod tests {
 
         let result = arrow_schema_to_json(file.path());
         assert!(result.is_err());
-        assert!(result
-            .unwrap_err()
-            .contains("Failed to read Arrow IPC file"));
+        assert!(
+            result
+                .unwrap_err()
+                .contains("Failed to read Arrow IPC file")
+        );
     }
 
     #[test]
@@ -0,0 +1,195 @@
+use crate::common_schema::FileFormat;
+use crate::dataset::{Dataset, dataset_parts};
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// A table, or a directory that may hold tables, found directly inside a source directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CatalogEntry {
+    /// An Arrow IPC or Parquet file, which is a table of its own.
+    File {
+        path: PathBuf,
+        stem: String,
+        /// The extension as written in the file name, which may not be lowercase.
+        extension: String,
+        format: FileFormat,
+    },
+    /// A directory holding `part-` files, which together make up one table.
+    Dataset {
+        path: PathBuf,
+        name: String,
+        parts: Vec<PathBuf>,
+    },
+    /// Any other directory, which may hold tables further down.
+    Directory { path: PathBuf, name: String },
+}
+
+impl CatalogEntry {
+    pub fn path(&self) -> &Path {
+        match self {
+            CatalogEntry::File { path, .. }
+            | CatalogEntry::Dataset { path, .. }
+            | CatalogEntry::Directory { path, .. } => path,
+        }
+    }
+}
+
+/// Checks if a file is hidden (starts with a dot).
+pub fn is_hidden(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.starts_with('.'))
+        .unwrap_or(false)
+}
+
+/// Lists the tables and directories directly inside `dir`, in file name order.
+///
+/// Hidden entries, entries whose name is not valid UTF-8 and files that are not Arrow IPC or
+/// Parquet files are left out. This is what decides which tables exist, for both `inspect`
+/// and the mount.
+pub fn list_entries(dir: &Path) -> Vec<CatalogEntry> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| !is_hidden(path))
+        .collect();
+    paths.sort();
+
+    let mut found = Vec::new();
+    for path in paths {
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        let name = name.to_string();
+        if path.is_dir() {
+            let parts = dataset_parts(&path);
+            if parts.is_empty() {
+                found.push(CatalogEntry::Directory { path, name });
+            } else {
+                found.push(CatalogEntry::Dataset { path, name, parts });
+            }
+        } else if let Some(format) = FileFormat::from_path(&path)
+            && let (Some(stem), Some(extension)) = (
+                path.file_stem().and_then(|s| s.to_str()),
+                path.extension().and_then(|e| e.to_str()),
+            )
+        {
+            found.push(CatalogEntry::File {
+                stem: stem.to_string(),
+                extension: extension.to_string(),
+                format,
+                path,
+            });
+        }
+    }
+    found
+}
+
+/// Recursively collects the tables below `root`, named after their file stem or directory.
+pub fn discover_datasets<P: AsRef<Path>>(root: P) -> Vec<Dataset> {
+    discover_datasets_except(root.as_ref(), &HashSet::new())
+}
+
+/// Recursively collects the tables below `root`, leaving out the files in `skip`.
+pub(crate) fn discover_datasets_except(root: &Path, skip: &HashSet<PathBuf>) -> Vec<Dataset> {
+    let mut datasets = Vec::new();
+    for entry in list_entries(root) {
+        match entry {
+            CatalogEntry::File { path, stem, .. } => {
+                if !skip.contains(&path) {
+                    datasets.push(Dataset::from_parts(stem, path.clone(), vec![path]));
+                }
+            }
+            CatalogEntry::Dataset {
+                path,
+                name,
+                mut parts,
+            } => {
+                parts.retain(|part| !skip.contains(part));
+                if !parts.is_empty() {
+                    datasets.push(Dataset::from_parts(name, path, parts));
+                }
+            }
+            CatalogEntry::Directory { path, .. } => {
+                datasets.extend(discover_datasets_except(&path, skip));
+            }
+        }
+    }
+    datasets
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+
+    fn touch(root: &Path, name: &str) {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        File::create(path).unwrap();
+    }
+
+    #[test]
+    fn test_list_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for name in [
+            "b.PARQUET",
+            "a.feather",
+            "notes.txt",
+            ".hidden.arrow",
+            "sales/part-0.parquet",
+            "sales/.part-1.parquet",
+            "nested/c.arrow",
+            ".cache/d.arrow",
+        ] {
+            touch(root, name);
+        }
+
+        let entries = list_entries(root);
+        let names: Vec<_> = entries
+            .iter()
+            .map(|entry| entry.path().file_name().unwrap().to_str().unwrap())
+            .collect();
+        assert_eq!(names, ["a.feather", "b.PARQUET", "nested", "sales"]);
+        assert!(matches!(
+            &entries[1],
+            CatalogEntry::File { stem, extension, format: FileFormat::Parquet, .. }
+                if stem == "b" && extension == "PARQUET"
+        ));
+        assert!(matches!(&entries[2], CatalogEntry::Directory { .. }));
+        // Hidden files are never parts of a dataset
+        assert_eq!(
+            entries[3],
+            CatalogEntry::Dataset {
+                path: root.join("sales"),
+                name: "sales".to_string(),
+                parts: vec![root.join("sales/part-0.parquet")],
+            }
+        );
+    }
+
+    #[test]
+    fn test_discover_datasets() {
+        let dir = tempfile::tempdir().unwrap();
+        for name in ["a.arrow", "nested/deeper/b.parquet", "sales/part-0.parquet"] {
+            touch(dir.path(), name);
+        }
+        let names: Vec<_> = discover_datasets(dir.path())
+            .into_iter()
+            .map(|dataset| dataset.name)
+            .collect();
+        assert_eq!(names, ["a", "b", "sales"]);
+    }
+
+    #[test]
+    fn test_is_hidden() {
+        assert!(is_hidden(Path::new(".hidden")));
+        assert!(is_hidden(Path::new(".hidden.arrow")));
+        assert!(!is_hidden(Path::new("visible.arrow")));
+    }
+}
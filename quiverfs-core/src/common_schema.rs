@@ -6,6 +6,19 @@ pub enum FileFormat {
     Parquet,
 }
 
+impl FileFormat {
+    /// Detects the format of a data file from its extension (`.arrow`, `.feather` or `.parquet`),
+    /// ignoring case.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<FileFormat> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        match extension.as_str() {
+            "arrow" | "feather" => Some(FileFormat::Arrow),
+            "parquet" => Some(FileFormat::Parquet),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub struct TableField {
     pub name: String,
@@ -180,4 +193,21 @@ mod tests {
         assert_eq!(schema.fields[0].nullable, false);
         assert_eq!(schema.fields[1].nullable, true);
     }
+
+    #[test]
+    fn test_file_format_from_path() {
+        assert_eq!(FileFormat::from_path("a.arrow"), Some(FileFormat::Arrow));
+        assert_eq!(FileFormat::from_path("a.feather"), Some(FileFormat::Arrow));
+        assert_eq!(
+            FileFormat::from_path("a.parquet"),
+            Some(FileFormat::Parquet)
+        );
+        assert_eq!(
+            FileFormat::from_path("A.PARQUET"),
+            Some(FileFormat::Parquet)
+        );
+        assert_eq!(FileFormat::from_path("a.Feather"), Some(FileFormat::Arrow));
+        assert_eq!(FileFormat::from_path("a.csv"), None);
+        assert_eq!(FileFormat::from_path("parquet"), None);
+    }
 }
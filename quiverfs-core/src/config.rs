@@ -0,0 +1,335 @@
+use crate::catalog::{discover_datasets_except, is_hidden};
+use crate::common_schema::FileFormat;
+use crate::dataset::Dataset;
+use crate::view::ColumnView;
+use arrow::datatypes::DataType;
+use glob::{MatchOptions, Pattern};
+use serde::{Deserialize, Deserializer};
+use std::collections::{BTreeMap, BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+/// `*` and `?` do not match `/` or a leading dot, while `**` matches any number of
+/// directories.
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: true,
+};
+
+/// A TOML configuration file declaring tables, such as:
+///
+/// ```toml
+/// [[table]]
+/// name = "sales"
+/// files = ["exports/sales-*.parquet", "archive/**/sales.parquet"]
+/// hide = ["internal_id"]
+/// rename = { amt = "amount" }
+/// cast = { amt = "Float64" }
+/// ```
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Config {
+    /// Whether the files that no declared table uses are still shown, as found by the scan.
+    #[serde(default = "scan_by_default")]
+    pub scan: bool,
+    #[serde(default, rename = "table")]
+    pub tables: Vec<TableConfig>,
+}
+
+/// A table made of the files matching its patterns, relative to the source directory.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TableConfig {
+    pub name: String,
+    /// Glob patterns such as `sales/*.parquet` or `events/**/*.arrow`.
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub hide: BTreeSet<String>,
+    #[serde(default)]
+    pub rename: BTreeMap<String, String>,
+    /// Arrow type names such as `Int64`, `Float64`, `Utf8` or `Timestamp(Millisecond, None)`.
+    #[serde(default, deserialize_with = "deserialize_types")]
+    pub cast: BTreeMap<String, DataType>,
+}
+
+/// The tables a configuration declares in one source directory.
+#[derive(Debug, Default)]
+pub struct DeclaredTables {
+    pub datasets: Vec<Dataset>,
+    /// Every file used by a declared table, which the scan leaves out.
+    pub files: HashSet<PathBuf>,
+}
+
+fn scan_by_default() -> bool {
+    true
+}
+
+fn deserialize_types<'de, D>(deserializer: D) -> Result<BTreeMap<String, DataType>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let names = BTreeMap::<String, String>::deserialize(deserializer)?;
+    names
+        .into_iter()
+        .map(|(column, name)| match DataType::from_str(&name) {
+            Ok(data_type) => Ok((column, data_type)),
+            Err(e) => Err(serde::de::Error::custom(format!(
+                "invalid type for column '{}': {}",
+                column, e
+            ))),
+        })
+        .collect()
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            scan: true,
+            tables: Vec::new(),
+        }
+    }
+}
+
+impl FromStr for Config {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let config: Config =
+            toml::from_str(text).map_err(|e| format!("Invalid configuration: {}", e))?;
+        let mut names = HashSet::new();
+        for table in &config.tables {
+            let name = &table.name;
+            if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+                return Err(format!(
+                    "Invalid table name '{}', expected a name without slashes",
+                    name
+                ));
+            }
+            if !names.insert(name) {
+                return Err(format!("Table '{}' is declared more than once", name));
+            }
+            if table.files.is_empty() {
+                return Err(format!("Table '{}' has no file patterns", name));
+            }
+            for pattern in &table.files {
+                Pattern::new(pattern).map_err(|e| {
+                    format!("Invalid pattern '{}' for table '{}': {}", pattern, name, e)
+                })?;
+            }
+        }
+        Ok(config)
+    }
+}
+
+impl Config {
+    /// Reads and checks a configuration file.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Config, String> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        text.parse()
+            .map_err(|e| format!("{}: {}", path.display(), e))
+    }
+
+    /// Builds the tables declared for the source directory `dir` from the files matching
+    /// their patterns. Tables that match no files are returned without parts.
+    pub fn declared_tables(&self, dir: &Path) -> DeclaredTables {
+        self.declared_tables_across(&[dir])
+    }
+
+    /// Builds the tables declared for source directories overlaid on one another, each from
+    /// the files matching its patterns in any of the directories. Tables are rooted in the
+    /// first directory, and those that match no files are returned without parts.
+    pub fn declared_tables_across(&self, dirs: &[&Path]) -> DeclaredTables {
+        let mut declared = DeclaredTables::default();
+        if self.tables.is_empty() {
+            return declared;
+        }
+        let mut files = Vec::new();
+        for dir in dirs {
+            let mut found = Vec::new();
+            visit_dir(dir, Path::new(""), &mut found);
+            found.sort();
+            files.extend(found.into_iter().map(|file| (*dir, file)));
+        }
+        for table in &self.tables {
+            let patterns: Vec<Pattern> = table
+                .files
+                .iter()
+                .filter_map(|pattern| Pattern::new(pattern).ok())
+                .collect();
+            let parts: Vec<PathBuf> = files
+                .iter()
+                .filter(|(_, file)| {
+                    patterns
+                        .iter()
+                        .any(|pattern| pattern.matches_path_with(file, MATCH_OPTIONS))
+                })
+                .map(|(dir, file)| dir.join(file))
+                .collect();
+            declared.files.extend(parts.iter().cloned());
+            let root = dirs[0].join(&table.name);
+            let mut dataset =
+                Dataset::from_parts(table.name.clone(), root, parts).with_view(table.view());
+            if dataset.parts.is_empty() {
+                dataset
+                    .diagnostics
+                    .push(format!("table '{}' matches no files", table.name));
+            }
+            declared.datasets.push(dataset);
+        }
+        declared
+    }
+
+    /// Lists the tables of `dir`: the declared ones first and then, unless scanning is turned
+    /// off, the ones found by the scan among the files no declared table uses.
+    pub fn datasets(&self, dir: &Path) -> Vec<Dataset> {
+        let DeclaredTables {
+            mut datasets,
+            files,
+        } = self.declared_tables(dir);
+        if self.scan {
+            datasets.extend(discover_datasets_except(dir, &files));
+        }
+        datasets
+    }
+}
+
+impl TableConfig {
+    pub fn view(&self) -> ColumnView {
+        ColumnView {
+            hide: self.hide.clone(),
+            rename: self.rename.clone(),
+            cast: self.cast.clone(),
+        }
+    }
+}
+
+/// Recursively collects the paths relative to `root` of the data files below `root/dir`,
+/// skipping hidden files and directories.
+fn visit_dir(root: &Path, dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(root.join(dir)) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = dir.join(entry.file_name());
+        if is_hidden(&path) {
+            continue;
+        }
+        if root.join(&path).is_dir() {
+            visit_dir(root, &path, files);
+        } else if FileFormat::from_path(&path).is_some() {
+            files.push(path);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{TextFormat, parse_text, write_parquet};
+
+    fn write_table(root: &Path, name: &str) {
+        let path = root.join(name);
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let (schema, batches) = parse_text(b"id\n1\n", TextFormat::Csv).unwrap();
+        write_parquet(&path, schema, &batches).unwrap();
+    }
+
+    #[test]
+    fn test_parse() {
+        let config: Config = r#"
+            scan = false
+
+            [[table]]
+            name = "sales"
+            files = ["sales/*.parquet"]
+            hide = ["internal_id"]
+            rename = { amt = "amount" }
+            cast = { amt = "Float64", day = "Timestamp(Millisecond, None)" }
+        "#
+        .parse()
+        .unwrap();
+        assert!(!config.scan);
+        let view = config.tables[0].view();
+        assert!(view.hide.contains("internal_id"));
+        assert_eq!(view.rename["amt"], "amount");
+        assert_eq!(view.cast["amt"], DataType::Float64);
+        assert!(matches!(view.cast["day"], DataType::Timestamp(..)));
+
+        for invalid in [
+            "[[table]]\nname = \"a/b\"\nfiles = [\"*.parquet\"]",
+            "[[table]]\nname = \"a\"\nfiles = []",
+            "[[table]]\nname = \"a\"\nfiles = [\"[\"]",
+            "[[table]]\nname = \"a\"\nfiles = [\"*.parquet\"]\ncast = { x = \"Int\" }",
+            "[[table]]\nname = \"a\"\nfiles = [\"*.parquet\"]\nhidden = [\"x\"]",
+            "[[table]]\nname = \"a\"\nfiles = [\"x\"]\n[[table]]\nname = \"a\"\nfiles = [\"y\"]",
+        ] {
+            assert!(invalid.parse::<Config>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_declared_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        for name in [
+            "exports/sales-2024.parquet",
+            "exports/sales-2025.parquet",
+            "exports/.sales-tmp.parquet",
+            "archive/2023/sales.parquet",
+            "exports/users.parquet",
+            "other.parquet",
+        ] {
+            write_table(root, name);
+        }
+        let config: Config = r#"
+            [[table]]
+            name = "sales"
+            files = ["exports/sales-*.parquet", "archive/**/sales.parquet"]
+
+            [[table]]
+            name = "empty"
+            files = ["*.csv"]
+        "#
+        .parse()
+        .unwrap();
+
+        let declared = config.declared_tables(root);
+        let sales = &declared.datasets[0];
+        assert_eq!(sales.root, root.join("sales"));
+        assert_eq!(
+            sales.parts,
+            [
+                root.join("archive/2023/sales.parquet"),
+                root.join("exports/sales-2024.parquet"),
+                root.join("exports/sales-2025.parquet"),
+            ]
+        );
+        assert_eq!(declared.files.len(), 3);
+        assert!(declared.datasets[1].parts.is_empty());
+
+        // Files used by declared tables are left out of the scan
+        let names: Vec<_> = config
+            .datasets(root)
+            .into_iter()
+            .map(|dataset| dataset.name)
+            .collect();
+        assert_eq!(names, ["sales", "empty", "users", "other"]);
+
+        // Overlaid sources are matched together
+        let other = tempfile::tempdir().unwrap();
+        write_table(other.path(), "exports/sales-2026.parquet");
+        let declared = config.declared_tables_across(&[root, other.path()]);
+        let sales = &declared.datasets[0];
+        assert_eq!(sales.root, root.join("sales"));
+        assert_eq!(sales.parts.len(), 4);
+        assert!(
+            sales
+                .parts
+                .contains(&other.path().join("exports/sales-2026.parquet"))
+        );
+    }
+}
@@ -0,0 +1,537 @@
+use crate::arrow_schema::{arrow_schema_to_json, schema_to_json};
+use crate::catalog::is_hidden;
+use crate::common_schema::{
+    ArrowSchemaInspector, FileFormat, ParquetSchemaInspector, SchemaInspectable, TableField,
+    TableSchema,
+};
+use crate::filter::{Predicate, filter_batches, read_filtered};
+use crate::parquet_schema::parquet_schema_to_json;
+use crate::rows::{chunk_row_counts, read_row_group, read_row_range};
+use crate::table_reader::{read_record_batches, read_schema};
+use crate::view::ColumnView;
+use arrow::datatypes::{FieldRef, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use serde_json::Value;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A logical table made of one or more data files with compatible schemas, such as the
+/// `part-0000.parquet ... part-0NNN.parquet` files written by Spark or pyarrow.
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    pub name: String,
+    /// The dataset directory, or the data file itself for single-file tables. Tables declared
+    /// in a configuration file have the source directory joined with their name.
+    pub root: PathBuf,
+    /// Data files that make up the table, in name order. Parts whose schema does not match
+    /// the first readable part are left out and reported in `diagnostics`.
+    pub parts: Vec<PathBuf>,
+    /// The schema of the parts, before the view is applied. A column is nullable if it is
+    /// nullable in any part.
+    pub schema: Option<SchemaRef>,
+    pub diagnostics: Vec<String>,
+    /// How the columns of a table declared in a configuration file are shown. `None` for
+    /// tables found by scanning.
+    pub view: Option<ColumnView>,
+}
+
+/// The rows of a dataset to read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selection {
+    /// Every row.
+    All,
+    /// The rows that match all predicates.
+    Where(Vec<Predicate>),
+    /// A range of row positions, counted across all parts.
+    Rows(Range<usize>),
+    /// A Parquet row group, numbered across all parts.
+    RowGroup(usize),
+}
+
+/// Checks if a file is a dataset part file (its name starts with `part-`).
+pub fn is_part_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .map(|n| n.starts_with("part-"))
+        .unwrap_or(false)
+}
+
+/// Describes the first difference between two schemas, ignoring nullability and metadata.
+fn schema_mismatch(expected: &Schema, actual: &Schema) -> Option<String> {
+    if expected.fields().len() != actual.fields().len() {
+        return Some(format!(
+            "has {} columns, expected {}",
+            actual.fields().len(),
+            expected.fields().len()
+        ));
+    }
+    expected
+        .fields()
+        .iter()
+        .zip(actual.fields().iter())
+        .find_map(|(e, a)| {
+            if e.name() != a.name() {
+                Some(format!(
+                    "has column '{}', expected '{}'",
+                    a.name(),
+                    e.name()
+                ))
+            } else if e.data_type() != a.data_type() {
+                Some(format!(
+                    "column '{}' has type {:?}, expected {:?}",
+                    a.name(),
+                    a.data_type(),
+                    e.data_type()
+                ))
+            } else {
+                None
+            }
+        })
+}
+
+/// Returns `expected` with the columns that are nullable in `actual` made nullable, or `None`
+/// if it already allows every null of `actual`.
+fn widen_nullability(expected: &Schema, actual: &Schema) -> Option<SchemaRef> {
+    let widen = |(e, a): (&FieldRef, &FieldRef)| a.is_nullable() && !e.is_nullable();
+    if !expected
+        .fields()
+        .iter()
+        .zip(actual.fields().iter())
+        .any(widen)
+    {
+        return None;
+    }
+    let fields: Vec<_> = expected
+        .fields()
+        .iter()
+        .zip(actual.fields().iter())
+        .map(|(e, a)| {
+            e.as_ref()
+                .clone()
+                .with_nullable(e.is_nullable() || a.is_nullable())
+        })
+        .collect();
+    Some(Arc::new(Schema::new_with_metadata(
+        fields,
+        expected.metadata().clone(),
+    )))
+}
+
+/// Gives `batches` the dataset schema `schema`, which can differ from the schema of a part in
+/// the nullability and metadata of its columns.
+fn conform(schema: &SchemaRef, batches: Vec<RecordBatch>) -> Result<Vec<RecordBatch>, String> {
+    batches
+        .into_iter()
+        .map(|batch| {
+            if batch.schema() == *schema {
+                return Ok(batch);
+            }
+            RecordBatch::try_new(schema.clone(), batch.columns().to_vec())
+                .map_err(|e| format!("Failed to read part with the dataset schema: {}", e))
+        })
+        .collect()
+}
+
+impl Dataset {
+    /// Builds a dataset from candidate part files, keeping only the parts whose schema
+    /// matches the first readable one. Parts may differ in which columns are nullable.
+    ///
+    /// A lone candidate is always kept so that read errors surface when the table is used.
+    pub fn from_parts(name: String, root: PathBuf, mut candidates: Vec<PathBuf>) -> Dataset {
+        candidates.sort();
+        let single = candidates.len() == 1;
+        let mut dataset = Dataset {
+            name,
+            root,
+            parts: Vec::new(),
+            schema: None,
+            diagnostics: Vec::new(),
+            view: None,
+        };
+        for part in candidates {
+            let schema = match read_schema(&part) {
+                Ok(schema) => schema,
+                Err(_) if single => {
+                    dataset.parts.push(part);
+                    continue;
+                }
+                Err(e) => {
+                    dataset
+                        .diagnostics
+                        .push(format!("{}: {}", part.display(), e));
+                    continue;
+                }
+            };
+            match &dataset.schema {
+                None => dataset.schema = Some(schema),
+                Some(expected) => {
+                    if let Some(mismatch) = schema_mismatch(expected, &schema) {
+                        dataset.diagnostics.push(format!(
+                            "{}: schema mismatch: {}",
+                            part.display(),
+                            mismatch
+                        ));
+                        continue;
+                    }
+                    if let Some(widened) = widen_nullability(expected, &schema) {
+                        dataset.schema = Some(widened);
+                    }
+                }
+            }
+            dataset.parts.push(part);
+        }
+        dataset
+    }
+
+    /// Shows the columns of the dataset through `view`, reporting the columns it names that
+    /// the dataset lacks in `diagnostics`.
+    pub fn with_view(mut self, view: ColumnView) -> Dataset {
+        if let Some(schema) = &self.schema {
+            for problem in view.check(schema) {
+                self.diagnostics
+                    .push(format!("table '{}' {}", self.name, problem));
+            }
+        }
+        self.view = Some(view);
+        self
+    }
+
+    /// Returns the schema of the rows returned by [`Dataset::read`], with the view applied.
+    pub fn output_schema(&self) -> Option<SchemaRef> {
+        let schema = self.schema.as_ref()?;
+        Some(match &self.view {
+            Some(view) => view.schema(schema),
+            None => schema.clone(),
+        })
+    }
+
+    /// Inspects the schema of the dataset using its first part.
+    pub fn table_schema(&self) -> Result<TableSchema, String> {
+        let first = self
+            .parts
+            .first()
+            .ok_or_else(|| format!("Dataset '{}' has no readable parts", self.name))?;
+        // Columns shown through a view are described by their Arrow type
+        if self.view.is_some()
+            && let (Some(schema), Some(format)) =
+                (self.output_schema(), FileFormat::from_path(first))
+        {
+            let fields = schema
+                .fields()
+                .iter()
+                .map(|f| TableField {
+                    name: f.name().to_string(),
+                    data_type: format!("{:?}", f.data_type()),
+                    nullable: f.is_nullable(),
+                })
+                .collect();
+            return Ok(TableSchema { fields, format });
+        }
+        match FileFormat::from_path(first) {
+            Some(FileFormat::Arrow) => ArrowSchemaInspector::inspect_schema(first),
+            Some(FileFormat::Parquet) => ParquetSchemaInspector::inspect_schema(first),
+            None => Err(format!("Unsupported file extension: {}", first.display())),
+        }
+    }
+
+    /// Describes the schema of the dataset as JSON using its first part, as served in
+    /// `schema.json`.
+    pub fn schema_json(&self) -> Result<Value, String> {
+        let first = self
+            .parts
+            .first()
+            .ok_or_else(|| format!("Dataset '{}' has no readable parts", self.name))?;
+        if self.view.is_some()
+            && let Some(schema) = self.output_schema()
+        {
+            return Ok(schema_to_json(&schema));
+        }
+        match FileFormat::from_path(first) {
+            Some(FileFormat::Arrow) => arrow_schema_to_json(first),
+            Some(FileFormat::Parquet) => parquet_schema_to_json(first).map_err(|e| e.to_string()),
+            None => Err(format!("Unsupported file extension: {}", first.display())),
+        }
+    }
+
+    /// Reads every part with `read`, in part order, and concatenates the batches.
+    fn read_parts<F>(&self, read: F) -> Result<(SchemaRef, Vec<RecordBatch>), String>
+    where
+        F: Fn(&Path) -> Result<(SchemaRef, Vec<RecordBatch>), String>,
+    {
+        let mut schema = self.schema.clone();
+        let mut batches = Vec::new();
+        for part in &self.parts {
+            let (part_schema, part_batches) = read(part)?;
+            batches.extend(conform(schema.get_or_insert(part_schema), part_batches)?);
+        }
+        let schema =
+            schema.ok_or_else(|| format!("Dataset '{}' has no readable parts", self.name))?;
+        Ok((schema, batches))
+    }
+
+    /// Reads the record batches of every part, in part order.
+    pub fn read_record_batches(&self) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+        self.read_parts(|part| read_record_batches(part))
+    }
+
+    /// Reads the rows of every part that match all `predicates`.
+    pub fn read_filtered(
+        &self,
+        predicates: &[Predicate],
+    ) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+        self.read_parts(|part| read_filtered(part, predicates))
+    }
+
+    /// Reads the rows in `rows`, counted across all parts. Parts that end before the range
+    /// starts are skipped using the row counts in their footers.
+    pub fn read_row_range(
+        &self,
+        rows: Range<usize>,
+    ) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+        let mut schema = self.schema.clone();
+        let mut batches = Vec::new();
+        let mut offset = 0;
+        for part in &self.parts {
+            if offset >= rows.end {
+                break;
+            }
+            let count: usize = chunk_row_counts(part)?.iter().sum();
+            let start = rows.start.max(offset) - offset;
+            let end = rows.end.min(offset + count).saturating_sub(offset);
+            offset += count;
+            if start >= end && schema.is_some() {
+                continue;
+            }
+            let (part_schema, part_batches) = read_row_range(part, start..end)?;
+            batches.extend(conform(schema.get_or_insert(part_schema), part_batches)?);
+        }
+        let schema =
+            schema.ok_or_else(|| format!("Dataset '{}' has no readable parts", self.name))?;
+        Ok((schema, batches))
+    }
+
+    /// Lists the Parquet row groups of every part as the part and the index of the row group
+    /// within it, in part order. Fails if any part is not a Parquet file.
+    pub fn row_groups(&self) -> Result<Vec<(&Path, usize)>, String> {
+        let mut row_groups = Vec::new();
+        for part in &self.parts {
+            if FileFormat::from_path(part) != Some(FileFormat::Parquet) {
+                return Err(format!("{} is not a Parquet file", part.display()));
+            }
+            let count = chunk_row_counts(part)?.len();
+            row_groups.extend((0..count).map(|index| (part.as_path(), index)));
+        }
+        Ok(row_groups)
+    }
+
+    /// Reads the rows picked by `selection`, with the view applied.
+    pub fn read(&self, selection: &Selection) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+        let Some(view) = &self.view else {
+            return self.read_parts_selection(selection);
+        };
+        let Selection::Where(predicates) = selection else {
+            let (schema, batches) = self.read_parts_selection(selection)?;
+            return view.apply(&schema, &batches);
+        };
+        // Comparisons on converted columns compare the converted values, so they are applied
+        // once the view is. The others can skip row groups using the statistics of the files.
+        let (converted, other): (Vec<Predicate>, Vec<Predicate>) =
+            predicates.iter().cloned().partition(|predicate| {
+                view.cast
+                    .contains_key(view.source_column(&predicate.column))
+            });
+        let other: Vec<Predicate> = other
+            .into_iter()
+            .map(|predicate| Predicate {
+                column: view.source_column(&predicate.column).to_string(),
+                ..predicate
+            })
+            .collect();
+        let (schema, batches) = self.read_filtered(&other)?;
+        let (schema, batches) = view.apply(&schema, &batches)?;
+        Ok((schema, filter_batches(&batches, &converted)?))
+    }
+
+    /// Reads the rows picked by `selection` as they are stored in the parts.
+    fn read_parts_selection(
+        &self,
+        selection: &Selection,
+    ) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+        match selection {
+            Selection::All => self.read_record_batches(),
+            Selection::Where(predicates) => self.read_filtered(predicates),
+            Selection::Rows(rows) => self.read_row_range(rows.clone()),
+            Selection::RowGroup(index) => {
+                let row_groups = self.row_groups()?;
+                let (part, index) = row_groups
+                    .get(*index)
+                    .ok_or_else(|| format!("Dataset '{}' has no row group {}", self.name, index))?;
+                let (part_schema, batches) = read_row_group(part, *index)?;
+                let schema = self.schema.clone().unwrap_or(part_schema);
+                let batches = conform(&schema, batches)?;
+                Ok((schema, batches))
+            }
+        }
+    }
+}
+
+/// Lists the data files directly inside `dir` if it holds dataset part files.
+///
+/// Returns an empty list for directories without any `part-` files. Hidden files are left
+/// out.
+pub fn dataset_parts<P: AsRef<Path>>(dir: P) -> Vec<PathBuf> {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && !is_hidden(path) && FileFormat::from_path(path).is_some())
+        .collect();
+    if files.iter().any(|path| is_part_file(path)) {
+        files
+    } else {
+        Vec::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, Int64Array};
+    use arrow::datatypes::{DataType, Field};
+    use parquet::arrow::ArrowWriter;
+    use std::fs::File;
+
+    fn write_parquet(path: &Path, batch: &RecordBatch) {
+        let mut writer =
+            ArrowWriter::try_new(File::create(path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(batch).unwrap();
+        writer.close().unwrap();
+    }
+
+    fn int32_batch(values: Vec<i32>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from(values))]).unwrap()
+    }
+
+    #[test]
+    fn test_dataset_from_parts_reports_mismatches() {
+        let dir = tempfile::tempdir().unwrap();
+        write_parquet(
+            &dir.path().join("part-0000.parquet"),
+            &int32_batch(vec![1, 2]),
+        );
+        write_parquet(&dir.path().join("part-0001.parquet"), &int32_batch(vec![3]));
+
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int64, false)]));
+        let wide =
+            RecordBatch::try_new(schema, vec![Arc::new(Int64Array::from(vec![4_i64]))]).unwrap();
+        write_parquet(&dir.path().join("part-0002.parquet"), &wide);
+        std::fs::write(dir.path().join("part-0003.parquet"), b"broken").unwrap();
+
+        let parts = dataset_parts(dir.path());
+        assert_eq!(parts.len(), 4);
+
+        let dataset = Dataset::from_parts("sales".to_string(), dir.path().to_path_buf(), parts);
+        assert_eq!(dataset.parts.len(), 2);
+        assert_eq!(dataset.diagnostics.len(), 2);
+        assert!(dataset.diagnostics[0].contains("column 'id' has type Int64"));
+
+        let (_, batches) = dataset.read_record_batches().unwrap();
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 3);
+        assert_eq!(dataset.table_schema().unwrap().fields[0].name, "id");
+    }
+
+    #[test]
+    fn test_parts_that_differ_in_nullability() {
+        let dir = tempfile::tempdir().unwrap();
+        write_parquet(&dir.path().join("part-0.parquet"), &int32_batch(vec![1]));
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, true)]));
+        let nullable = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(Int32Array::from(vec![None, Some(2)]))],
+        )
+        .unwrap();
+        write_parquet(&dir.path().join("part-1.parquet"), &nullable);
+
+        let dataset = Dataset::from_parts(
+            "ids".to_string(),
+            dir.path().to_path_buf(),
+            dataset_parts(dir.path()),
+        );
+        assert_eq!(dataset.parts.len(), 2);
+        assert!(dataset.schema.as_ref().unwrap().field(0).is_nullable());
+        for selection in [
+            Selection::All,
+            Selection::Rows(0..3),
+            Selection::RowGroup(0),
+        ] {
+            let (schema, batches) = dataset.read(&selection).unwrap();
+            assert!(schema.field(0).is_nullable());
+            assert!(batches.iter().all(|batch| batch.schema() == schema));
+        }
+    }
+
+    #[test]
+    fn test_single_file_dataset_keeps_unreadable_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.parquet");
+        std::fs::write(&path, b"broken").unwrap();
+
+        let dataset = Dataset::from_parts("broken".to_string(), path.clone(), vec![path]);
+        assert_eq!(dataset.parts.len(), 1);
+        assert!(dataset.diagnostics.is_empty());
+        assert!(
+            dataset
+                .table_schema()
+                .unwrap_err()
+                .contains("Failed to read Parquet file")
+        );
+        assert!(dataset.read_record_batches().is_err());
+    }
+
+    #[test]
+    fn test_read_row_range_across_parts() {
+        let dir = tempfile::tempdir().unwrap();
+        write_parquet(
+            &dir.path().join("part-0.parquet"),
+            &int32_batch(vec![0, 1, 2]),
+        );
+        write_parquet(
+            &dir.path().join("part-1.parquet"),
+            &int32_batch(vec![3, 4, 5]),
+        );
+        let dataset = Dataset::from_parts(
+            "ids".to_string(),
+            dir.path().to_path_buf(),
+            dataset_parts(dir.path()),
+        );
+
+        let (_, batches) = dataset.read(&Selection::Rows(2..5)).unwrap();
+        let ids: Vec<i32> = batches
+            .iter()
+            .flat_map(|b| {
+                let ids = b.column(0).as_any().downcast_ref::<Int32Array>().unwrap();
+                ids.values().to_vec()
+            })
+            .collect();
+        assert_eq!(ids, [2, 3, 4]);
+
+        let row_groups = dataset.row_groups().unwrap();
+        assert_eq!(row_groups.len(), 2);
+        assert!(row_groups[1].0.ends_with("part-1.parquet"));
+        let (_, batches) = dataset.read(&Selection::RowGroup(1)).unwrap();
+        assert_eq!(batches[0].num_rows(), 3);
+        assert!(dataset.read(&Selection::RowGroup(2)).is_err());
+    }
+
+    #[test]
+    fn test_dataset_parts_without_part_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_parquet(&dir.path().join("users.parquet"), &int32_batch(vec![1]));
+        assert!(dataset_parts(dir.path()).is_empty());
+    }
+}
@@ -1,29 +1,21 @@
+use crate::catalog::is_hidden;
+use crate::common_schema::FileFormat;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-/// Checks if a file is hidden (starts with a dot).
-fn is_hidden(path: &Path) -> bool {
-    path.file_name()
-        .and_then(|n| n.to_str())
-        .map(|n| n.starts_with('.'))
-        .unwrap_or(false)
-}
-
 /// Recursively visits directories and collects supported files.
-fn visit_dir(dir: &Path, files: &mut Vec<PathBuf>, exts: &[&str]) {
+fn visit_dir(dir: &Path, files: &mut Vec<PathBuf>) {
     if let Ok(entries) = fs::read_dir(dir) {
         for entry in entries.flatten() {
             let path = entry.path();
             if path.is_dir() && !is_hidden(&path) {
-                visit_dir(&path, files, exts);
+                visit_dir(&path, files);
             } else if is_hidden(&path) {
                 continue; // Skip hidden files
-            } else if let Some(ext) = path.extension().and_then(|e| e.to_str()) {
-                if exts.contains(&ext) {
-                    if let Ok(abs_path) = path.canonicalize() {
-                        files.push(abs_path);
-                    }
-                }
+            } else if FileFormat::from_path(&path).is_some()
+                && let Ok(abs_path) = path.canonicalize()
+            {
+                files.push(abs_path);
             }
         }
     }
@@ -32,10 +24,9 @@ fn visit_dir(dir: &Path, files: &mut Vec<PathBuf>, exts: &[&str]) {
 /// Recursively scans a directory for .arrow, .feather, or .parquet files and returns their absolute paths.
 pub fn discover_data_files<P: AsRef<Path>>(root: P) -> Vec<PathBuf> {
     let mut files = Vec::new();
-    let supported_exts = ["arrow", "feather", "parquet"];
     let root = root.as_ref();
     if root.is_dir() {
-        visit_dir(root, &mut files, &supported_exts);
+        visit_dir(root, &mut files);
     }
     files
 }
@@ -43,10 +34,8 @@ pub fn discover_data_files<P: AsRef<Path>>(root: P) -> Vec<PathBuf> {
 #[cfg(test)]
 mod tests {
     use super::discover_data_files;
-    use super::is_hidden;
     use std::fs::{self, File};
     use std::io::Write;
-    use std::path::Path;
 
     #[test]
     fn test_discover_data_files() {
@@ -129,11 +118,4 @@ mod tests {
             );
         }
     }
-
-    #[test]
-    fn test_is_hidden() {
-        assert!(is_hidden(Path::new(".hidden")));
-        assert!(is_hidden(Path::new(".hidden.arrow")));
-        assert!(!is_hidden(Path::new("visible.arrow")));
-    }
 }
@@ -0,0 +1,379 @@
+use crate::common_schema::FileFormat;
+use crate::table_reader::read_record_batches;
+use arrow::array::{Array, ArrayRef, BooleanArray, Datum, Scalar, StringArray};
+use arrow::compute::kernels::cmp;
+use arrow::compute::{and, cast, filter_record_batch};
+use arrow::datatypes::{DataType, Schema, SchemaRef};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::arrow_reader::statistics::StatisticsConverter;
+use parquet::file::metadata::RowGroupMetaData;
+use parquet::schema::types::SchemaDescriptor;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Eq,
+    NotEq,
+    Lt,
+    LtEq,
+    Gt,
+    GtEq,
+}
+
+impl Operator {
+    fn symbol(self) -> &'static str {
+        match self {
+            Operator::Eq => "=",
+            Operator::NotEq => "!=",
+            Operator::Lt => "<",
+            Operator::LtEq => "<=",
+            Operator::Gt => ">",
+            Operator::GtEq => ">=",
+        }
+    }
+
+    fn compare(self, lhs: &dyn Datum, rhs: &dyn Datum) -> Result<BooleanArray, ArrowError> {
+        match self {
+            Operator::Eq => cmp::eq(lhs, rhs),
+            Operator::NotEq => cmp::neq(lhs, rhs),
+            Operator::Lt => cmp::lt(lhs, rhs),
+            Operator::LtEq => cmp::lt_eq(lhs, rhs),
+            Operator::Gt => cmp::gt(lhs, rhs),
+            Operator::GtEq => cmp::gt_eq(lhs, rhs),
+        }
+    }
+}
+
+/// A comparison between a top-level column and a literal value, such as `country=DE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Predicate {
+    pub column: String,
+    pub op: Operator,
+    pub value: String,
+}
+
+impl fmt::Display for Predicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}{}", self.column, self.op.symbol(), self.value)
+    }
+}
+
+impl Predicate {
+    /// Parses a path segment such as `country=DE`, `amount>100` or `id!=3`.
+    pub fn parse(segment: &str) -> Result<Predicate, String> {
+        let start = segment
+            .find(['=', '!', '<', '>'])
+            .ok_or_else(|| format!("No comparison operator in '{}'", segment))?;
+        let rest = &segment[start..];
+        // Two-character operators are checked first so that `>=` is not read as `>`
+        let (op, len) = if rest.starts_with(">=") {
+            (Operator::GtEq, 2)
+        } else if rest.starts_with("<=") {
+            (Operator::LtEq, 2)
+        } else if rest.starts_with("!=") {
+            (Operator::NotEq, 2)
+        } else if rest.starts_with('=') {
+            (Operator::Eq, 1)
+        } else if rest.starts_with('>') {
+            (Operator::Gt, 1)
+        } else if rest.starts_with('<') {
+            (Operator::Lt, 1)
+        } else {
+            return Err(format!("Invalid comparison operator in '{}'", segment));
+        };
+        let column = &segment[..start];
+        if column.is_empty() {
+            return Err(format!("Missing column name in '{}'", segment));
+        }
+        Ok(Predicate {
+            column: column.to_string(),
+            op,
+            value: segment[start + len..].to_string(),
+        })
+    }
+
+    /// Converts the value into a literal of the given type.
+    fn literal(&self, data_type: &DataType) -> Result<Scalar<ArrayRef>, String> {
+        let value = StringArray::from(vec![self.value.as_str()]);
+        let array = cast(&value, data_type).map_err(|e| {
+            format!(
+                "Cannot compare '{}' with {:?}: {}",
+                self.value, data_type, e
+            )
+        })?;
+        // Casting is lenient and turns unparseable values into nulls
+        if array.is_null(0) {
+            return Err(format!(
+                "Cannot compare '{}' with {:?}",
+                self.value, data_type
+            ));
+        }
+        Ok(Scalar::new(array))
+    }
+
+    /// Checks that the column exists in `schema` and that the value can be compared with it.
+    pub fn validate(&self, schema: &Schema) -> Result<(), String> {
+        let field = schema
+            .field_with_name(&self.column)
+            .map_err(|_| format!("Unknown column '{}'", self.column))?;
+        let literal = self.literal(field.data_type())?;
+        let column = arrow::array::new_empty_array(field.data_type());
+        self.op
+            .compare(&column, &literal)
+            .map(|_| ())
+            .map_err(|e| format!("Cannot filter on '{}': {}", self.column, e))
+    }
+
+    /// Evaluates the predicate against every row of `batch`. Rows with a null value do not match.
+    pub fn evaluate(&self, batch: &RecordBatch) -> Result<BooleanArray, String> {
+        let column = batch
+            .column_by_name(&self.column)
+            .ok_or_else(|| format!("Unknown column '{}'", self.column))?;
+        let literal = self.literal(column.data_type())?;
+        self.op
+            .compare(column, &literal)
+            .map_err(|e| format!("Failed to evaluate '{}': {}", self, e))
+    }
+
+    /// Decides for each row group whether it may hold matching rows, given the min and max
+    /// statistics of the column. Row groups without statistics are always kept.
+    fn may_match(&self, mins: &ArrayRef, maxes: &ArrayRef) -> Result<Vec<bool>, String> {
+        let literal = self.literal(mins.data_type())?;
+        let keep = |array: Result<BooleanArray, ArrowError>| -> Result<Vec<bool>, String> {
+            let array = array.map_err(|e| format!("Failed to evaluate '{}': {}", self, e))?;
+            Ok(array.iter().map(|v| v.unwrap_or(true)).collect())
+        };
+        let combine = |a: Vec<bool>, b: Vec<bool>, f: fn(bool, bool) -> bool| {
+            a.into_iter().zip(b).map(|(a, b)| f(a, b)).collect()
+        };
+        Ok(match self.op {
+            Operator::Eq => combine(
+                keep(cmp::lt_eq(mins, &literal))?,
+                keep(cmp::gt_eq(maxes, &literal))?,
+                |a, b| a && b,
+            ),
+            Operator::NotEq => combine(
+                keep(cmp::neq(mins, &literal))?,
+                keep(cmp::neq(maxes, &literal))?,
+                |a, b| a || b,
+            ),
+            Operator::Lt => keep(cmp::lt(mins, &literal))?,
+            Operator::LtEq => keep(cmp::lt_eq(mins, &literal))?,
+            Operator::Gt => keep(cmp::gt(maxes, &literal))?,
+            Operator::GtEq => keep(cmp::gt_eq(maxes, &literal))?,
+        })
+    }
+}
+
+/// Lists the row groups that may hold rows matching every predicate, using the column
+/// statistics in the Parquet footer.
+pub fn matching_row_groups(
+    row_groups: &[RowGroupMetaData],
+    arrow_schema: &Schema,
+    parquet_schema: &SchemaDescriptor,
+    predicates: &[Predicate],
+) -> Result<Vec<usize>, String> {
+    let mut keep = vec![true; row_groups.len()];
+    for predicate in predicates {
+        let converter =
+            StatisticsConverter::try_new(&predicate.column, arrow_schema, parquet_schema)
+                .map_err(|e| format!("Failed to read statistics: {}", e))?;
+        let mins = converter
+            .row_group_mins(row_groups.iter())
+            .map_err(|e| format!("Failed to read statistics: {}", e))?;
+        let maxes = converter
+            .row_group_maxes(row_groups.iter())
+            .map_err(|e| format!("Failed to read statistics: {}", e))?;
+        for (keep, may_match) in keep.iter_mut().zip(predicate.may_match(&mins, &maxes)?) {
+            *keep &= may_match;
+        }
+    }
+    Ok((0..row_groups.len()).filter(|i| keep[*i]).collect())
+}
+
+/// Keeps the rows of `batches` that match every predicate.
+pub fn filter_batches(
+    batches: &[RecordBatch],
+    predicates: &[Predicate],
+) -> Result<Vec<RecordBatch>, String> {
+    let mut filtered = Vec::new();
+    for batch in batches {
+        let mut mask: Option<BooleanArray> = None;
+        for predicate in predicates {
+            let matches = predicate.evaluate(batch)?;
+            mask = Some(match mask {
+                Some(mask) => and(&mask, &matches).map_err(|e| e.to_string())?,
+                None => matches,
+            });
+        }
+        let batch = match mask {
+            Some(mask) => filter_record_batch(batch, &mask).map_err(|e| e.to_string())?,
+            None => batch.clone(),
+        };
+        if batch.num_rows() > 0 {
+            filtered.push(batch);
+        }
+    }
+    Ok(filtered)
+}
+
+/// Reads the rows of an Arrow IPC or Parquet file that match every predicate.
+///
+/// Parquet row groups whose statistics rule out a match are skipped without being decoded.
+pub fn read_filtered<P: AsRef<Path>>(
+    path: P,
+    predicates: &[Predicate],
+) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+    let path = path.as_ref();
+    let (schema, batches) = if FileFormat::from_path(path) == Some(FileFormat::Parquet) {
+        let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+            .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+        let row_groups = matching_row_groups(
+            builder.metadata().row_groups(),
+            builder.schema(),
+            builder.parquet_schema(),
+            predicates,
+        )?;
+        let schema = builder.schema().clone();
+        let reader = builder
+            .with_row_groups(row_groups)
+            .build()
+            .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+        let batches = reader
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read Parquet batch: {}", e))?;
+        (schema, batches)
+    } else {
+        read_record_batches(path)?
+    };
+    Ok((schema, filter_batches(&batches, predicates)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::Field;
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+
+    fn sample_batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("amount", DataType::Int32, false),
+            Field::new("country", DataType::Utf8, true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![50, 150, 250, 350])),
+                Arc::new(StringArray::from(vec![
+                    Some("DE"),
+                    Some("FR"),
+                    None,
+                    Some("DE"),
+                ])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_parse_predicate() {
+        let p = Predicate::parse("amount>=100").unwrap();
+        assert_eq!(p.column, "amount");
+        assert_eq!(p.op, Operator::GtEq);
+        assert_eq!(p.value, "100");
+        assert_eq!(p.to_string(), "amount>=100");
+
+        assert_eq!(Predicate::parse("country=DE").unwrap().op, Operator::Eq);
+        assert_eq!(Predicate::parse("id!=3").unwrap().op, Operator::NotEq);
+        assert_eq!(Predicate::parse("id<3").unwrap().op, Operator::Lt);
+        assert_eq!(Predicate::parse("name=").unwrap().value, "");
+        assert!(Predicate::parse("data.csv").is_err());
+        assert!(Predicate::parse("=DE").is_err());
+        assert!(Predicate::parse("id!3").is_err());
+    }
+
+    #[test]
+    fn test_validate_predicate() {
+        let batch = sample_batch();
+        let schema = batch.schema();
+        assert!(
+            Predicate::parse("amount>100")
+                .unwrap()
+                .validate(&schema)
+                .is_ok()
+        );
+        assert!(
+            Predicate::parse("country=DE")
+                .unwrap()
+                .validate(&schema)
+                .is_ok()
+        );
+        assert!(
+            Predicate::parse("amount>lots")
+                .unwrap()
+                .validate(&schema)
+                .is_err()
+        );
+        assert!(
+            Predicate::parse("missing=1")
+                .unwrap()
+                .validate(&schema)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn test_filter_batches() {
+        let predicates = [
+            Predicate::parse("country=DE").unwrap(),
+            Predicate::parse("amount>100").unwrap(),
+        ];
+        let filtered = filter_batches(&[sample_batch()], &predicates).unwrap();
+        assert_eq!(filtered.len(), 1);
+        let amounts = filtered[0]
+            .column(0)
+            .as_any()
+            .downcast_ref::<Int32Array>()
+            .unwrap();
+        assert_eq!(amounts.values(), &[350]);
+    }
+
+    #[test]
+    fn test_read_filtered_skips_row_groups() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.parquet");
+        let batch = sample_batch();
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), Some(props))
+                .unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let file = File::open(&path).unwrap();
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file).unwrap();
+        let predicates = [Predicate::parse("amount>200").unwrap()];
+        let row_groups = matching_row_groups(
+            builder.metadata().row_groups(),
+            builder.schema(),
+            builder.parquet_schema(),
+            &predicates,
+        )
+        .unwrap();
+        assert_eq!(row_groups, [1]);
+
+        let (_, batches) = read_filtered(&path, &predicates).unwrap();
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 2);
+    }
+}
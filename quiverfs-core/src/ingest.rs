@@ -0,0 +1,233 @@
+use arrow::csv::reader::Format;
+use arrow::datatypes::SchemaRef;
+use arrow::ipc::writer::FileWriter;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::ArrowWriter;
+use parquet::file::properties::WriterProperties;
+use std::fs::File;
+use std::io::{BufReader, Cursor, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+/// A text format that can be converted to Parquet.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextFormat {
+    /// Comma-separated values with a header row.
+    Csv,
+    /// Newline-delimited JSON objects.
+    Jsonl,
+}
+
+impl TextFormat {
+    /// Detects the format from a `.csv` or `.jsonl` extension, ignoring case.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<TextFormat> {
+        match path
+            .as_ref()
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase()
+            .as_str()
+        {
+            "csv" => Some(TextFormat::Csv),
+            "jsonl" => Some(TextFormat::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// Parses CSV or newline-delimited JSON into record batches, inferring the schema from the
+/// whole input.
+pub fn parse_text(
+    data: &[u8],
+    format: TextFormat,
+) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+    let (schema, batches) = match format {
+        TextFormat::Csv => {
+            let csv_format = Format::default().with_header(true);
+            let (schema, _) = csv_format
+                .infer_schema(Cursor::new(data), None)
+                .map_err(|e| format!("Failed to infer CSV schema: {}", e))?;
+            let schema = Arc::new(schema);
+            let reader = arrow::csv::ReaderBuilder::new(schema.clone())
+                .with_format(csv_format)
+                .build(Cursor::new(data))
+                .map_err(|e| format!("Failed to read CSV: {}", e))?;
+            let batches = reader
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read CSV: {}", e))?;
+            (schema, batches)
+        }
+        TextFormat::Jsonl => {
+            let (schema, _) = arrow::json::reader::infer_json_schema(BufReader::new(data), None)
+                .map_err(|e| format!("Failed to infer JSON schema: {}", e))?;
+            let schema = Arc::new(schema);
+            let reader = arrow::json::ReaderBuilder::new(schema.clone())
+                .build(BufReader::new(data))
+                .map_err(|e| format!("Failed to read JSON: {}", e))?;
+            let batches = reader
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read JSON: {}", e))?;
+            (schema, batches)
+        }
+    };
+    if schema.fields().is_empty() {
+        return Err("Input has no columns".to_string());
+    }
+    Ok((schema, batches))
+}
+
+/// Creates or replaces `dest` with the output of `write`.
+///
+/// The file is written under a hidden temporary name next to `dest` and renamed once complete,
+/// so that readers never see a partial file.
+fn write_atomically<F>(dest: &Path, write: F) -> Result<(), String>
+where
+    F: FnOnce(File) -> Result<(), String>,
+{
+    let name = dest
+        .file_name()
+        .and_then(|n| n.to_str())
+        .ok_or_else(|| format!("Invalid destination: {}", dest.display()))?;
+    let tmp = dest.with_file_name(format!(".{}.tmp", name));
+    File::create(&tmp)
+        .map_err(|e| format!("Failed to create file: {}", e))
+        .and_then(write)
+        .and_then(|_| {
+            std::fs::rename(&tmp, dest).map_err(|e| format!("Failed to create file: {}", e))
+        })
+        .inspect_err(|_| {
+            let _ = std::fs::remove_file(&tmp);
+        })
+}
+
+/// Writes record batches in the Parquet format to `out`.
+pub fn write_parquet_to<W: Write + Send>(
+    out: W,
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+) -> Result<(), String> {
+    write_parquet_with_properties(out, schema, batches, None)
+}
+
+fn write_parquet_with_properties<W: Write + Send>(
+    out: W,
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+    properties: Option<WriterProperties>,
+) -> Result<(), String> {
+    let mut writer = ArrowWriter::try_new(out, schema, properties)
+        .map_err(|e| format!("Failed to write Parquet file: {}", e))?;
+    for batch in batches {
+        writer
+            .write(batch)
+            .map_err(|e| format!("Failed to write Parquet file: {}", e))?;
+    }
+    writer
+        .close()
+        .map(|_| ())
+        .map_err(|e| format!("Failed to write Parquet file: {}", e))
+}
+
+/// Writes record batches in the Arrow IPC file format to `out`.
+pub fn write_arrow_to<W: Write>(
+    out: W,
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+) -> Result<(), String> {
+    let mut writer = FileWriter::try_new(out, &schema)
+        .map_err(|e| format!("Failed to write Arrow IPC file: {}", e))?;
+    for batch in batches {
+        writer
+            .write(batch)
+            .map_err(|e| format!("Failed to write Arrow IPC file: {}", e))?;
+    }
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to write Arrow IPC file: {}", e))
+}
+
+/// Writes record batches to a Parquet file at `dest`.
+pub fn write_parquet(
+    dest: &Path,
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+) -> Result<(), String> {
+    write_atomically(dest, |file| write_parquet_to(file, schema, batches))
+}
+
+/// Writes record batches to a Parquet file at `dest` with the given compression, row group
+/// size and key-value metadata.
+pub fn write_parquet_with(
+    dest: &Path,
+    schema: SchemaRef,
+    batches: &[RecordBatch],
+    properties: WriterProperties,
+) -> Result<(), String> {
+    write_atomically(dest, |file| {
+        write_parquet_with_properties(file, schema, batches, Some(properties))
+    })
+}
+
+/// Writes record batches to an Arrow IPC file at `dest`.
+pub fn write_arrow(dest: &Path, schema: SchemaRef, batches: &[RecordBatch]) -> Result<(), String> {
+    write_atomically(dest, |file| write_arrow_to(file, schema, batches))
+}
+
+/// Converts CSV or newline-delimited JSON into a Parquet file at `dest`, returning the number
+/// of rows written.
+pub fn ingest_text(data: &[u8], format: TextFormat, dest: &Path) -> Result<usize, String> {
+    let (schema, batches) = parse_text(data, format)?;
+    write_parquet(dest, schema, &batches)?;
+    Ok(batches.iter().map(|b| b.num_rows()).sum())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::read_record_batches;
+    use arrow::datatypes::DataType;
+
+    #[test]
+    fn test_ingest_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("sales.parquet");
+        let csv = b"id,country,amount\n1,DE,10.5\n2,FR,\n";
+        assert_eq!(ingest_text(csv, TextFormat::Csv, &dest).unwrap(), 2);
+
+        let (schema, batches) = read_record_batches(&dest).unwrap();
+        assert_eq!(schema.field(0).data_type(), &DataType::Int64);
+        assert_eq!(schema.field(1).data_type(), &DataType::Utf8);
+        assert_eq!(schema.field(2).data_type(), &DataType::Float64);
+        assert_eq!(batches[0].num_rows(), 2);
+        // Only the finished file is left behind
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[test]
+    fn test_ingest_jsonl() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("events.parquet");
+        let jsonl = b"{\"id\": 1, \"meta\": {\"a\": true}}\n{\"id\": 2, \"meta\": null}\n";
+        assert_eq!(ingest_text(jsonl, TextFormat::Jsonl, &dest).unwrap(), 2);
+        let (schema, _) = read_record_batches(&dest).unwrap();
+        assert!(matches!(schema.field(1).data_type(), DataType::Struct(_)));
+    }
+
+    #[test]
+    fn test_ingest_rejects_bad_input() {
+        let dir = tempfile::tempdir().unwrap();
+        let dest = dir.path().join("bad.parquet");
+        assert!(ingest_text(b"{\"id\": 1\n", TextFormat::Jsonl, &dest).is_err());
+        assert!(ingest_text(b"a,b\n1,2,3\n", TextFormat::Csv, &dest).is_err());
+        assert!(ingest_text(b"", TextFormat::Csv, &dest).is_err());
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn test_text_format_from_path() {
+        assert_eq!(TextFormat::from_path("a.csv"), Some(TextFormat::Csv));
+        assert_eq!(TextFormat::from_path("a.jsonl"), Some(TextFormat::Jsonl));
+        assert_eq!(TextFormat::from_path("a.CSV"), Some(TextFormat::Csv));
+        assert_eq!(TextFormat::from_path("a.json"), None);
+    }
+}
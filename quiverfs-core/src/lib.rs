@@ -1,8 +1,26 @@
+pub mod append;
 pub mod arrow_schema;
+pub mod catalog;
 pub mod common_schema;
+pub mod config;
+pub mod dataset;
 pub mod file_discovery;
+pub mod filter;
+pub mod ingest;
+pub mod parquet_metadata;
 pub mod parquet_schema;
+pub mod render;
+pub mod rows;
+pub mod stats;
+pub mod table_reader;
+pub mod view;
+pub mod xattr;
 
 pub use arrow_schema::arrow_schema_to_json;
+pub use catalog::{CatalogEntry, discover_datasets, list_entries};
+pub use config::Config;
+pub use dataset::{Dataset, Selection};
 pub use file_discovery::discover_data_files;
 pub use parquet_schema::parquet_schema_to_json;
+pub use table_reader::{has_complete_footer, is_feather_v1, read_record_batches, read_schema};
+pub use view::ColumnView;
@@ -0,0 +1,152 @@
+use parquet::file::metadata::{ColumnChunkMetaData, RowGroupMetaData};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::file::statistics::Statistics;
+use serde_json::{Value, json};
+use std::fmt::Display;
+use std::fs::File;
+use std::path::Path;
+
+/// Converts one bound of a column's statistics to JSON. Byte arrays are shown as text when
+/// they hold valid UTF-8, and as hex otherwise.
+fn bound_to_json(stats: &Statistics, min: bool) -> Value {
+    macro_rules! pick {
+        ($stats:expr) => {
+            if min {
+                $stats.min_opt()
+            } else {
+                $stats.max_opt()
+            }
+        };
+    }
+    fn bytes(value: &[u8]) -> Value {
+        match std::str::from_utf8(value) {
+            Ok(text) => json!(text),
+            Err(_) => json!(value.iter().map(|b| format!("{b:02x}")).collect::<String>()),
+        }
+    }
+    match stats {
+        Statistics::Boolean(s) => json!(pick!(s)),
+        Statistics::Int32(s) => json!(pick!(s)),
+        Statistics::Int64(s) => json!(pick!(s)),
+        Statistics::Int96(s) => json!(pick!(s).map(|v| v.to_string())),
+        Statistics::Float(s) => json!(pick!(s)),
+        Statistics::Double(s) => json!(pick!(s)),
+        Statistics::ByteArray(s) => pick!(s).map_or(Value::Null, |v| bytes(v.data())),
+        Statistics::FixedLenByteArray(s) => pick!(s).map_or(Value::Null, |v| bytes(v.data())),
+    }
+}
+
+/// Names a codec without the compression level, which is not stored in the file.
+fn codec_name(codec: impl Display) -> String {
+    let name = codec.to_string();
+    name.split('(').next().unwrap_or_default().to_string()
+}
+
+fn column_chunk_to_json(column: &ColumnChunkMetaData) -> Value {
+    let statistics = column.statistics().map(|stats| {
+        json!({
+            "min": bound_to_json(stats, true),
+            "max": bound_to_json(stats, false),
+            "null_count": stats.null_count_opt(),
+            "distinct_count": stats.distinct_count_opt(),
+        })
+    });
+    json!({
+        "path": column.column_path().string(),
+        "physical_type": column.column_type().to_string(),
+        "compression": codec_name(column.compression()),
+        "encodings": column.encodings().iter().map(|e| e.to_string()).collect::<Vec<_>>(),
+        "num_values": column.num_values(),
+        "compressed_size": column.compressed_size(),
+        "uncompressed_size": column.uncompressed_size(),
+        "data_page_offset": column.data_page_offset(),
+        "dictionary_page_offset": column.dictionary_page_offset(),
+        "statistics": statistics,
+    })
+}
+
+/// Describes a row group as JSON: its row count, byte sizes and, for each column chunk, its
+/// compression, encodings, sizes, offsets and statistics.
+pub fn row_group_to_json(row_group: &RowGroupMetaData) -> Value {
+    json!({
+        "num_rows": row_group.num_rows(),
+        "total_byte_size": row_group.total_byte_size(),
+        "compressed_size": row_group.compressed_size(),
+        "file_offset": row_group.file_offset(),
+        "columns": row_group.columns().iter().map(column_chunk_to_json).collect::<Vec<_>>(),
+    })
+}
+
+/// Opens a Parquet file and returns the metadata of one of its row groups as JSON.
+pub fn parquet_row_group_to_json<P: AsRef<Path>>(path: P, index: usize) -> Result<Value, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let reader = SerializedFileReader::new(file)
+        .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+    let metadata = reader.metadata();
+    if index >= metadata.num_row_groups() {
+        return Err(format!(
+            "Row group {} out of range, file has {}",
+            index,
+            metadata.num_row_groups()
+        ));
+    }
+    Ok(row_group_to_json(metadata.row_group(index)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use parquet::arrow::ArrowWriter;
+    use parquet::basic::{Compression, ZstdLevel};
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_parquet_row_group_to_json() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.parquet");
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![3, 1, 2, 4])),
+                Arc::new(StringArray::from(vec![
+                    Some("b"),
+                    None,
+                    Some("a"),
+                    Some("c"),
+                ])),
+            ],
+        )
+        .unwrap();
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(3)
+            .set_compression(Compression::ZSTD(ZstdLevel::default()))
+            .build();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path).unwrap(), schema, Some(props)).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let json = parquet_row_group_to_json(&path, 0).unwrap();
+        assert_eq!(json["num_rows"], 3);
+        let id = &json["columns"][0];
+        assert_eq!(id["path"], "id");
+        assert_eq!(id["physical_type"], "INT32");
+        assert_eq!(id["compression"], "ZSTD");
+        assert_eq!(id["statistics"]["min"], 1);
+        assert_eq!(id["statistics"]["max"], 3);
+        let name = &json["columns"][1];
+        assert_eq!(name["statistics"]["min"], "a");
+        assert_eq!(name["statistics"]["null_count"], 1);
+
+        assert_eq!(parquet_row_group_to_json(&path, 1).unwrap()["num_rows"], 1);
+        assert!(parquet_row_group_to_json(&path, 2).is_err());
+    }
+}
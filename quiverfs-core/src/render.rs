@@ -0,0 +1,251 @@
+use crate::ingest::{write_arrow_to, write_parquet_to};
+use arrow::datatypes::{Schema, SchemaRef};
+use arrow::json::writer::{LineDelimited, WriterBuilder};
+use arrow::record_batch::RecordBatch;
+use arrow::util::display::{ArrayFormatter, FormatOptions};
+use std::fmt::Write;
+
+/// Renders one column of a table as newline-delimited text, one value per line.
+///
+/// Null values become empty lines so that line numbers still match row numbers. For the same
+/// reason line breaks within values are written as `\n` and `\r`, and backslashes as `\\`.
+pub fn column_to_text(batches: &[RecordBatch], column: usize) -> Result<Vec<u8>, String> {
+    let options = FormatOptions::default();
+    let mut out = String::new();
+    let mut value = String::new();
+    for batch in batches {
+        if column >= batch.num_columns() {
+            return Err(format!("Column index {} out of range", column));
+        }
+        let array = batch.column(column);
+        let formatter = ArrayFormatter::try_new(array.as_ref(), &options)
+            .map_err(|e| format!("Failed to format column: {}", e))?;
+        for row in 0..array.len() {
+            value.clear();
+            write!(value, "{}", formatter.value(row))
+                .map_err(|e| format!("Failed to format value: {}", e))?;
+            for c in value.chars() {
+                match c {
+                    '\\' => out.push_str("\\\\"),
+                    '\n' => out.push_str("\\n"),
+                    '\r' => out.push_str("\\r"),
+                    c => out.push(c),
+                }
+            }
+            out.push('\n');
+        }
+    }
+    Ok(out.into_bytes())
+}
+
+/// Quotes a CSV field if it contains a delimiter, quote or line break, doubling embedded quotes.
+fn push_csv_field(out: &mut String, value: &str) {
+    if value.contains([',', '"', '\r', '\n']) {
+        out.push('"');
+        out.push_str(&value.replace('"', "\"\""));
+        out.push('"');
+    } else {
+        out.push_str(value);
+    }
+}
+
+/// Renders a table as RFC 4180 CSV with a header row.
+///
+/// Rows end with a single `\n` so that line-oriented shell tools keep working. Nested
+/// values are written using their display form, and nulls become empty fields.
+pub fn table_to_csv(schema: &Schema, batches: &[RecordBatch]) -> Result<Vec<u8>, String> {
+    let options = FormatOptions::default();
+    let mut out = String::new();
+    for (i, field) in schema.fields().iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+        push_csv_field(&mut out, field.name());
+    }
+    out.push('\n');
+
+    let mut value = String::new();
+    for batch in batches {
+        let formatters = batch
+            .columns()
+            .iter()
+            .map(|array| ArrayFormatter::try_new(array.as_ref(), &options))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to format column: {}", e))?;
+        for row in 0..batch.num_rows() {
+            for (i, formatter) in formatters.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                value.clear();
+                write!(value, "{}", formatter.value(row))
+                    .map_err(|e| format!("Failed to format value: {}", e))?;
+                push_csv_field(&mut out, &value);
+            }
+            out.push('\n');
+        }
+    }
+    Ok(out.into_bytes())
+}
+
+/// Renders a table as newline-delimited JSON, one object per row.
+///
+/// Struct and list columns become nested JSON objects and arrays, and nulls are kept as
+/// explicit `null` values so every row has the same keys.
+pub fn table_to_jsonl(batches: &[RecordBatch]) -> Result<Vec<u8>, String> {
+    let mut writer = WriterBuilder::new()
+        .with_explicit_nulls(true)
+        .build::<_, LineDelimited>(Vec::new());
+    for batch in batches {
+        writer
+            .write(batch)
+            .map_err(|e| format!("Failed to write JSON row: {}", e))?;
+    }
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to write JSON row: {}", e))?;
+    Ok(writer.into_inner())
+}
+
+/// Encodes a table as a Parquet file, keeping its schema metadata, field metadata and nested
+/// types.
+pub fn table_to_parquet(schema: SchemaRef, batches: &[RecordBatch]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    write_parquet_to(&mut out, schema, batches)?;
+    Ok(out)
+}
+
+/// Encodes a table as an Arrow IPC file.
+pub fn table_to_arrow(schema: SchemaRef, batches: &[RecordBatch]) -> Result<Vec<u8>, String> {
+    let mut out = Vec::new();
+    write_arrow_to(&mut out, schema, batches)?;
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Array, ArrayRef, Int32Array, Int64Array, StringArray, StructArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_column_to_text() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec![Some("a"), None, Some("c")])),
+            ],
+        )
+        .unwrap();
+
+        let batches = [batch.clone(), batch];
+        assert_eq!(column_to_text(&batches, 0).unwrap(), b"1\n2\n3\n1\n2\n3\n");
+        assert_eq!(column_to_text(&batches, 1).unwrap(), b"a\n\nc\na\n\nc\n");
+        assert!(column_to_text(&batches, 2).is_err());
+    }
+
+    #[test]
+    fn test_column_to_text_escapes_line_breaks() {
+        let schema = Arc::new(Schema::new(vec![Field::new("note", DataType::Utf8, true)]));
+        let notes = StringArray::from(vec![Some("two\nlines"), Some("c:\\dir\r\n"), None]);
+        let batch = RecordBatch::try_new(schema, vec![Arc::new(notes)]).unwrap();
+        assert_eq!(
+            column_to_text(&[batch], 0).unwrap(),
+            b"two\\nlines\nc:\\\\dir\\r\\n\n\n"
+        );
+    }
+
+    #[test]
+    fn test_table_to_csv_quotes_fields() {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("note, text", DataType::Utf8, true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec![
+                    Some("plain"),
+                    None,
+                    Some("say \"hi\",\nbye"),
+                ])),
+            ],
+        )
+        .unwrap();
+
+        let csv = table_to_csv(&schema, &[batch]).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            "id,\"note, text\"\n1,plain\n2,\n3,\"say \"\"hi\"\",\nbye\"\n"
+        );
+    }
+
+    #[test]
+    fn test_table_to_jsonl_nests_structs() {
+        let nested = Arc::new(Field::new("nested", DataType::Int64, true));
+        let meta = StructArray::from(vec![(
+            nested.clone(),
+            Arc::new(Int64Array::from(vec![Some(42), None])) as ArrayRef,
+        )]);
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("meta", meta.data_type().clone(), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema,
+            vec![Arc::new(Int32Array::from(vec![1, 2])), Arc::new(meta)],
+        )
+        .unwrap();
+
+        let jsonl = table_to_jsonl(&[batch]).unwrap();
+        assert_eq!(
+            String::from_utf8(jsonl).unwrap(),
+            "{\"id\":1,\"meta\":{\"nested\":42}}\n{\"id\":2,\"meta\":{\"nested\":null}}\n"
+        );
+    }
+
+    #[test]
+    fn test_table_to_parquet_and_arrow_keep_metadata() {
+        let nested = Arc::new(Field::new("nested", DataType::Int64, true));
+        let meta = StructArray::from(vec![(
+            nested,
+            Arc::new(Int64Array::from(vec![Some(42), None])) as ArrayRef,
+        )]);
+        let id = Field::new("id", DataType::Int32, false).with_metadata(
+            [("unit".to_string(), "count".to_string())]
+                .into_iter()
+                .collect(),
+        );
+        let schema = Arc::new(Schema::new(vec![
+            id,
+            Field::new("meta", meta.data_type().clone(), true),
+        ]));
+        let batch = RecordBatch::try_new(
+            schema.clone(),
+            vec![Arc::new(Int32Array::from(vec![1, 2])), Arc::new(meta)],
+        )
+        .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let parquet = dir.path().join("t.parquet");
+        let arrow = dir.path().join("t.arrow");
+        let data = table_to_parquet(schema.clone(), std::slice::from_ref(&batch)).unwrap();
+        std::fs::write(&parquet, data).unwrap();
+        let data = table_to_arrow(schema.clone(), std::slice::from_ref(&batch)).unwrap();
+        std::fs::write(&arrow, data).unwrap();
+
+        for path in [parquet, arrow] {
+            let (read_schema, batches) = crate::read_record_batches(&path).unwrap();
+            assert_eq!(read_schema.fields(), schema.fields());
+            assert_eq!(batches.len(), 1);
+            assert_eq!(batches[0], batch);
+        }
+    }
+}
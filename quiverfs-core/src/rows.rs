@@ -0,0 +1,264 @@
+use crate::common_schema::FileFormat;
+use arrow::datatypes::SchemaRef;
+use arrow::ipc::reader::FileReader;
+use arrow::ipc::{root_as_footer, root_as_message};
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::Path;
+
+/// Reads the number of rows in each record batch of an Arrow IPC file from the message
+/// headers listed in its footer, without reading any batch bodies.
+fn ipc_batch_lengths(path: &Path) -> Result<Vec<usize>, String> {
+    let invalid = |e: &dyn std::fmt::Display| format!("Failed to read Arrow IPC file: {}", e);
+    let mut file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let file_len = file.metadata().map_err(|e| invalid(&e))?.len();
+
+    // The file ends with the footer, its length as a little-endian i32 and the magic bytes.
+    // Lengths are checked against the file size before anything is allocated for them.
+    let mut trailer = [0; 10];
+    file.seek(SeekFrom::End(-10)).map_err(|e| invalid(&e))?;
+    file.read_exact(&mut trailer).map_err(|e| invalid(&e))?;
+    let footer_len = i32::from_le_bytes(trailer[..4].try_into().unwrap()).max(0) as i64;
+    if footer_len as u64 > file_len - 10 {
+        return Err(invalid(&"footer is longer than the file"));
+    }
+    let mut footer = vec![0; footer_len as usize];
+    file.seek(SeekFrom::End(-10 - footer_len))
+        .map_err(|e| invalid(&e))?;
+    file.read_exact(&mut footer).map_err(|e| invalid(&e))?;
+    let footer = root_as_footer(&footer).map_err(|e| invalid(&e))?;
+
+    let mut lengths = Vec::new();
+    for block in footer.recordBatches().iter().flatten() {
+        let offset = block.offset().max(0) as u64;
+        let metadata_len = block.metaDataLength().max(0) as u64;
+        if offset.saturating_add(metadata_len) > file_len {
+            return Err(invalid(&"message header extends past the end of the file"));
+        }
+        let mut message = vec![0; metadata_len as usize];
+        file.seek(SeekFrom::Start(offset))
+            .map_err(|e| invalid(&e))?;
+        file.read_exact(&mut message).map_err(|e| invalid(&e))?;
+        // Messages start with a continuation marker followed by their length, or only their
+        // length in files written before the marker was introduced
+        let start = if message.starts_with(&[0xff; 4]) {
+            8
+        } else {
+            4
+        };
+        let message = message
+            .get(start..)
+            .ok_or_else(|| invalid(&"truncated message"))
+            .and_then(|m| root_as_message(m).map_err(|e| invalid(&e)))?;
+        let batch = message
+            .header_as_record_batch()
+            .ok_or_else(|| invalid(&"block is not a record batch"))?;
+        lengths.push(batch.length().max(0) as usize);
+    }
+    Ok(lengths)
+}
+
+/// Lists the number of rows in each Parquet row group or Arrow IPC record batch of a file,
+/// reading only its footer and message headers.
+pub fn chunk_row_counts<P: AsRef<Path>>(path: P) -> Result<Vec<usize>, String> {
+    let path = path.as_ref();
+    match FileFormat::from_path(path) {
+        Some(FileFormat::Arrow) => ipc_batch_lengths(path),
+        Some(FileFormat::Parquet) => {
+            let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+            let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+                .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+            Ok(builder
+                .metadata()
+                .row_groups()
+                .iter()
+                .map(|group| group.num_rows().max(0) as usize)
+                .collect())
+        }
+        None => Err(format!("Unsupported file extension: {}", path.display())),
+    }
+}
+
+/// Finds the chunks that overlap `rows`, returning their indices and the row number at which
+/// the first of them starts.
+fn overlapping_chunks(counts: &[usize], rows: &Range<usize>) -> (Vec<usize>, usize) {
+    let mut chunks = Vec::new();
+    let mut first_row = 0;
+    let mut start = 0;
+    for (index, count) in counts.iter().enumerate() {
+        let end = start + count;
+        if start < rows.end && end > rows.start && *count > 0 {
+            if chunks.is_empty() {
+                first_row = start;
+            }
+            chunks.push(index);
+        }
+        start = end;
+    }
+    (chunks, first_row)
+}
+
+/// Reads the rows in `rows` of an Arrow IPC or Parquet file. Row groups and record batches
+/// before the range are skipped without being decoded.
+///
+/// Ranges past the end of the file are cut short.
+pub fn read_row_range<P: AsRef<Path>>(
+    path: P,
+    rows: Range<usize>,
+) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+    let path = path.as_ref();
+    let counts = chunk_row_counts(path)?;
+    let (chunks, first_row) = overlapping_chunks(&counts, &rows);
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+
+    if FileFormat::from_path(path) == Some(FileFormat::Parquet) {
+        let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+            .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+        let schema = builder.schema().clone();
+        let reader = builder
+            .with_row_groups(chunks)
+            .with_offset(rows.start.saturating_sub(first_row))
+            .with_limit(rows.len())
+            .build()
+            .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+        let batches = reader
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("Failed to read Parquet batch: {}", e))?;
+        return Ok((schema, batches));
+    }
+
+    let mut reader = FileReader::try_new(BufReader::new(file), None)
+        .map_err(|e| format!("Failed to read Arrow IPC file: {}", e))?;
+    let schema = reader.schema();
+    let mut batches = Vec::new();
+    if let Some(first) = chunks.first() {
+        reader
+            .set_index(*first)
+            .map_err(|e| format!("Failed to read Arrow IPC file: {}", e))?;
+        let mut start = first_row;
+        for batch in reader.take(chunks.len()) {
+            let batch = batch.map_err(|e| format!("Failed to read Arrow IPC batch: {}", e))?;
+            let from = rows.start.saturating_sub(start);
+            let to = (rows.end - start).min(batch.num_rows());
+            start += batch.num_rows();
+            batches.push(batch.slice(from, to - from));
+        }
+    }
+    Ok((schema, batches))
+}
+
+/// Reads one row group of a Parquet file.
+pub fn read_row_group<P: AsRef<Path>>(
+    path: P,
+    index: usize,
+) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+        .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+    let count = builder.metadata().num_row_groups();
+    if index >= count {
+        return Err(format!(
+            "Row group {} out of range, file has {}",
+            index, count
+        ));
+    }
+    let schema = builder.schema().clone();
+    let reader = builder
+        .with_row_groups(vec![index])
+        .build()
+        .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+    let batches = reader
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("Failed to read Parquet batch: {}", e))?;
+    Ok((schema, batches))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::ipc::writer::FileWriter;
+    use parquet::arrow::ArrowWriter;
+    use parquet::file::properties::WriterProperties;
+    use std::sync::Arc;
+
+    fn batch(values: Range<i32>) -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![Field::new("id", DataType::Int32, false)]));
+        RecordBatch::try_new(schema, vec![Arc::new(Int32Array::from_iter_values(values))]).unwrap()
+    }
+
+    fn ids(batches: &[RecordBatch]) -> Vec<i32> {
+        batches
+            .iter()
+            .flat_map(|b| {
+                let ids = b.column(0).as_any().downcast_ref::<Int32Array>().unwrap();
+                ids.values().to_vec()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_read_row_range_arrow() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.arrow");
+        let mut writer =
+            FileWriter::try_new(File::create(&path).unwrap(), &batch(0..0).schema()).unwrap();
+        for values in [0..10, 10..20, 20..30] {
+            writer.write(&batch(values)).unwrap();
+        }
+        writer.finish().unwrap();
+
+        assert_eq!(chunk_row_counts(&path).unwrap(), [10, 10, 10]);
+        let (_, batches) = read_row_range(&path, 15..22).unwrap();
+        assert_eq!(ids(&batches), (15..22).collect::<Vec<_>>());
+        let (_, batches) = read_row_range(&path, 25..100).unwrap();
+        assert_eq!(ids(&batches), (25..30).collect::<Vec<_>>());
+        let (_, batches) = read_row_range(&path, 40..50).unwrap();
+        assert!(batches.is_empty());
+    }
+
+    #[test]
+    fn test_ipc_batch_lengths_rejects_oversized_footer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.arrow");
+        let mut writer =
+            FileWriter::try_new(File::create(&path).unwrap(), &batch(0..0).schema()).unwrap();
+        writer.write(&batch(0..10)).unwrap();
+        writer.finish().unwrap();
+
+        // Claim a footer of 2 GiB in a file of a few hundred bytes
+        let mut bytes = std::fs::read(&path).unwrap();
+        let trailer = bytes.len() - 10;
+        bytes[trailer..trailer + 4].copy_from_slice(&i32::MAX.to_le_bytes());
+        std::fs::write(&path, bytes).unwrap();
+        let error = chunk_row_counts(&path).unwrap_err();
+        assert!(error.contains("footer is longer than the file"), "{error}");
+    }
+
+    #[test]
+    fn test_read_row_range_parquet() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.parquet");
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(10)
+            .build();
+        let data = batch(0..30);
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path).unwrap(), data.schema(), Some(props)).unwrap();
+        writer.write(&data).unwrap();
+        writer.close().unwrap();
+
+        assert_eq!(chunk_row_counts(&path).unwrap(), [10, 10, 10]);
+        let (_, batches) = read_row_range(&path, 15..22).unwrap();
+        assert_eq!(ids(&batches), (15..22).collect::<Vec<_>>());
+        let (_, batches) = read_row_range(&path, 0..1).unwrap();
+        assert_eq!(ids(&batches), [0]);
+        let (_, batches) = read_row_group(&path, 2).unwrap();
+        assert_eq!(ids(&batches), (20..30).collect::<Vec<_>>());
+        assert!(read_row_group(&path, 3).is_err());
+    }
+}
@@ -0,0 +1,438 @@
+use crate::common_schema::FileFormat;
+use crate::dataset::{Dataset, Selection};
+use crate::render::table_to_jsonl;
+use crate::table_reader::read_record_batches;
+use arrow::array::{Array, ArrayRef};
+use arrow::compute::{SortOptions, concat, sort_to_indices};
+use arrow::datatypes::{DataType, Field, Schema};
+use arrow::error::ArrowError;
+use arrow::record_batch::RecordBatch;
+use arrow::row::{OwnedRow, RowConverter, SortField};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::arrow_reader::statistics::StatisticsConverter;
+use serde_json::{Value, json};
+use std::collections::HashSet;
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+/// The most distinct values counted for a column, and the most bytes they may take once
+/// encoded as rows. Columns with more have no distinct count.
+const MAX_DISTINCT_VALUES: usize = 100_000;
+const MAX_DISTINCT_BYTES: usize = 16 << 20;
+
+/// The distinct values of a column seen so far, encoded as rows.
+struct DistinctValues {
+    converter: RowConverter,
+    rows: HashSet<OwnedRow>,
+    bytes: usize,
+}
+
+/// Statistics of one column, gathered part by part.
+struct ColumnStats {
+    /// Candidate minimums and maximums, such as those of each Parquet row group, or `None`
+    /// once a part without usable bounds was seen.
+    bounds: Option<(Vec<ArrayRef>, Vec<ArrayRef>)>,
+    /// The number of nulls, or `None` once a part without a null count was seen.
+    null_count: Option<u64>,
+    /// The distinct values of the rows read so far, or `None` once there were too many.
+    values: Option<DistinctValues>,
+    /// The sum of the distinct counts in the footers of the Parquet row groups seen and their
+    /// number, or `None` once a row group without a distinct count was seen.
+    footer_distinct: Option<(u64, usize)>,
+}
+
+impl ColumnStats {
+    fn new(data_type: &DataType) -> Self {
+        let converter = RowConverter::new(vec![SortField::new(data_type.clone())]).ok();
+        ColumnStats {
+            bounds: Some((Vec::new(), Vec::new())),
+            null_count: Some(0),
+            values: converter.map(|converter| DistinctValues {
+                converter,
+                rows: HashSet::new(),
+                bytes: 0,
+            }),
+            footer_distinct: Some((0, 0)),
+        }
+    }
+
+    fn add_bounds(&mut self, min: ArrayRef, max: ArrayRef) {
+        if let Some((mins, maxes)) = &mut self.bounds {
+            mins.push(min);
+            maxes.push(max);
+        }
+    }
+
+    fn add_nulls(&mut self, count: Option<u64>) {
+        self.null_count = self.null_count.zip(count).map(|(a, b)| a + b);
+    }
+
+    /// Records the values of an Arrow IPC column, whose statistics are computed from the data.
+    fn add_array(&mut self, array: &ArrayRef) {
+        self.add_nulls(Some(array.null_count() as u64));
+        match (extreme(array, false), extreme(array, true)) {
+            (Ok(min), Ok(max)) => {
+                if let (Some(min), Some(max)) = (min, max) {
+                    self.add_bounds(min, max);
+                }
+            }
+            _ => self.bounds = None,
+        }
+        if let Some(values) = &mut self.values {
+            let Ok(rows) = values
+                .converter
+                .convert_columns(std::slice::from_ref(array))
+            else {
+                self.values = None;
+                return;
+            };
+            for row in rows.iter() {
+                if values.rows.insert(row.owned()) {
+                    values.bytes += row.as_ref().len();
+                }
+                if values.rows.len() > MAX_DISTINCT_VALUES || values.bytes > MAX_DISTINCT_BYTES {
+                    self.values = None;
+                    return;
+                }
+            }
+        }
+    }
+
+    fn to_json(&self, name: &str) -> Value {
+        let bound = |arrays: &[ArrayRef], descending: bool| {
+            let refs: Vec<&dyn Array> = arrays.iter().map(|a| a.as_ref()).collect();
+            let all = concat(&refs).ok()?;
+            extreme(&all, descending)
+                .ok()?
+                .map(|value| value_to_json(&value))
+        };
+        let (min, max) = match &self.bounds {
+            Some((mins, maxes)) if !mins.is_empty() => (bound(mins, false), bound(maxes, true)),
+            _ => (None, None),
+        };
+        // The distinct counts of row groups cannot be combined, as they may share values, so
+        // their sum is only an upper bound
+        let values = self.values.as_ref().map(|values| values.rows.len() as u64);
+        let (distinct_count, exact) = match (self.footer_distinct, values) {
+            (Some((_, 0)), Some(values)) => (Some(values), true),
+            (Some((count, 1)), Some(0)) => (Some(count), true),
+            (Some((count, _)), Some(values)) => (Some(count + values), false),
+            _ => (None, false),
+        };
+        json!({
+            "name": name,
+            "min": min,
+            "max": max,
+            "null_count": self.null_count,
+            "distinct_count": distinct_count,
+            "distinct_count_exact": exact,
+        })
+    }
+}
+
+/// Returns the smallest or, when `descending`, the largest non-null value of an array as a
+/// one-element array, or `None` when every value is null.
+fn extreme(array: &ArrayRef, descending: bool) -> Result<Option<ArrayRef>, ArrowError> {
+    let options = SortOptions {
+        descending,
+        nulls_first: false,
+    };
+    let indices = sort_to_indices(array, Some(options), Some(1))?;
+    Ok(indices
+        .values()
+        .first()
+        .map(|index| *index as usize)
+        .filter(|index| array.is_valid(*index))
+        .map(|index| array.slice(index, 1)))
+}
+
+/// Converts a one-element array to the JSON value it has in `data.jsonl`.
+fn value_to_json(value: &ArrayRef) -> Value {
+    let field = Field::new("value", value.data_type().clone(), true);
+    let batch = RecordBatch::try_new(Arc::new(Schema::new(vec![field])), vec![value.clone()]);
+    batch
+        .map_err(|e| e.to_string())
+        .and_then(|batch| table_to_jsonl(&[batch]))
+        .ok()
+        .and_then(|line| serde_json::from_slice::<Value>(&line).ok())
+        .map_or(Value::Null, |row| row["value"].clone())
+}
+
+/// Adds the footer statistics of a Parquet file, returning its number of rows.
+fn add_parquet_part(path: &Path, columns: &mut [ColumnStats]) -> Result<u64, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+        .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+    let row_groups = builder.metadata().row_groups();
+    let row_counts: Vec<u64> = row_groups
+        .iter()
+        .map(|group| group.num_rows().max(0) as u64)
+        .collect();
+
+    for (field, column) in builder.schema().fields().iter().zip(columns.iter_mut()) {
+        // Nested columns are spread over several leaf columns and have no statistics of their own
+        let Ok(converter) =
+            StatisticsConverter::try_new(field.name(), builder.schema(), builder.parquet_schema())
+        else {
+            column.bounds = None;
+            column.null_count = None;
+            continue;
+        };
+        let null_counts = converter.row_group_null_counts(row_groups.iter()).ok();
+        let null_counts = null_counts.filter(|counts| counts.null_count() == 0);
+        column.add_nulls(
+            null_counts
+                .as_ref()
+                .map(|counts| counts.values().iter().sum()),
+        );
+
+        // Writers rarely record distinct counts. Those they do leave out nulls, which count as
+        // a value here.
+        let distinct_counts = converter.parquet_column_index().and_then(|index| {
+            let counts = row_groups.iter().enumerate().map(|(i, group)| {
+                let count = group.column(index).statistics()?.distinct_count_opt()?;
+                let nulls = null_counts.as_ref()?.value(i);
+                Some(count + u64::from(nulls > 0))
+            });
+            counts.collect::<Option<Vec<u64>>>()
+        });
+        column.footer_distinct =
+            column
+                .footer_distinct
+                .zip(distinct_counts)
+                .map(|((sum, groups), counts)| {
+                    (sum + counts.iter().sum::<u64>(), groups + counts.len())
+                });
+
+        let mins = converter.row_group_mins(row_groups.iter());
+        let maxes = converter.row_group_maxes(row_groups.iter());
+        match (mins, maxes, &null_counts) {
+            // A row group without bounds only has known bounds when all its values are null
+            (Ok(mins), Ok(maxes), Some(null_counts))
+                if (0..row_counts.len()).all(|i| {
+                    (mins.is_valid(i) && maxes.is_valid(i)) || null_counts.value(i) == row_counts[i]
+                }) =>
+            {
+                column.add_bounds(mins, maxes)
+            }
+            _ => column.bounds = None,
+        }
+    }
+    Ok(row_counts.iter().sum())
+}
+
+/// Adds the statistics of an Arrow IPC file, computed from all of its rows, returning its
+/// number of rows.
+fn add_arrow_part(path: &Path, columns: &mut [ColumnStats]) -> Result<u64, String> {
+    let (_, batches) = read_record_batches(path)?;
+    Ok(add_batches(&batches, columns))
+}
+
+/// Adds the statistics of every row of `batches`, returning their number of rows.
+fn add_batches(batches: &[RecordBatch], columns: &mut [ColumnStats]) -> u64 {
+    let mut num_rows = 0;
+    for batch in batches {
+        num_rows += batch.num_rows() as u64;
+        for (array, column) in batch.columns().iter().zip(columns.iter_mut()) {
+            column.add_array(array);
+        }
+    }
+    num_rows
+}
+
+/// Describes the row count of a table and the minimum, maximum, null count and distinct count
+/// of each top-level column as JSON.
+///
+/// Parquet parts only contribute their footer statistics, so nested columns and columns
+/// written without statistics have unknown values. Their distinct count is only known when
+/// every row group records one, and is exact for a single row group; otherwise it is the sum
+/// over the row groups, an upper bound, and `distinct_count_exact` is false. Arrow IPC parts
+/// are read in full and give exact values, as do tables shown through a view, whose columns
+/// may be converted. Distinct counts above 100,000 values, or 16 MiB of them, are unknown.
+pub fn table_stats(dataset: &Dataset) -> Result<Value, String> {
+    let schema = dataset
+        .output_schema()
+        .ok_or_else(|| format!("Dataset '{}' has no readable parts", dataset.name))?;
+    let mut columns: Vec<ColumnStats> = schema
+        .fields()
+        .iter()
+        .map(|field| ColumnStats::new(field.data_type()))
+        .collect();
+    let mut num_rows = 0;
+    if dataset.view.is_some() {
+        let (_, batches) = dataset.read(&Selection::All)?;
+        num_rows = add_batches(&batches, &mut columns);
+    } else {
+        for part in &dataset.parts {
+            num_rows += match FileFormat::from_path(part) {
+                Some(FileFormat::Parquet) => add_parquet_part(part, &mut columns)?,
+                Some(FileFormat::Arrow) => add_arrow_part(part, &mut columns)?,
+                None => return Err(format!("Unsupported file extension: {}", part.display())),
+            };
+        }
+    }
+    let columns: Vec<Value> = schema
+        .fields()
+        .iter()
+        .zip(&columns)
+        .map(|(field, column)| column.to_json(field.name()))
+        .collect();
+    Ok(json!({
+        "num_rows": num_rows,
+        "columns": columns,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::{write_arrow, write_parquet};
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::SchemaRef;
+    use parquet::arrow::ArrowWriter;
+    use parquet::data_type::Int32Type;
+    use parquet::file::properties::WriterProperties;
+    use parquet::file::writer::SerializedFileWriter;
+    use parquet::schema::parser::parse_message_type;
+
+    fn schema() -> SchemaRef {
+        Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("country", DataType::Utf8, true),
+        ]))
+    }
+
+    fn batch(ids: Vec<i32>, countries: Vec<Option<&str>>) -> RecordBatch {
+        RecordBatch::try_new(
+            schema(),
+            vec![
+                Arc::new(Int32Array::from(ids)),
+                Arc::new(StringArray::from(countries)),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_table_stats_arrow() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.arrow");
+        let batches = [
+            batch(vec![3, 1], vec![Some("DE"), None]),
+            batch(vec![7, 2], vec![Some("FR"), Some("DE")]),
+        ];
+        write_arrow(&path, schema(), &batches).unwrap();
+
+        let dataset = Dataset::from_parts("t".to_string(), path.clone(), vec![path]);
+        let stats = table_stats(&dataset).unwrap();
+        assert_eq!(stats["num_rows"], 4);
+        let id = &stats["columns"][0];
+        assert_eq!((&id["min"], &id["max"]), (&json!(1), &json!(7)));
+        assert_eq!(id["distinct_count"], 4);
+        assert_eq!(id["distinct_count_exact"], true);
+        let country = &stats["columns"][1];
+        assert_eq!(country["name"], "country");
+        assert_eq!(
+            (&country["min"], &country["max"]),
+            (&json!("DE"), &json!("FR"))
+        );
+        assert_eq!(country["null_count"], 1);
+        // Null is counted as one of the distinct values
+        assert_eq!(country["distinct_count"], 3);
+    }
+
+    #[test]
+    fn test_table_stats_parquet() {
+        let dir = tempfile::tempdir().unwrap();
+        let first = dir.path().join("part-00000.parquet");
+        let second = dir.path().join("part-00001.parquet");
+        let props = WriterProperties::builder()
+            .set_max_row_group_size(2)
+            .build();
+        let data = batch(vec![5, 4, 9], vec![Some("IT"), None, None]);
+        let mut writer =
+            ArrowWriter::try_new(File::create(&first).unwrap(), schema(), Some(props)).unwrap();
+        writer.write(&data).unwrap();
+        writer.close().unwrap();
+        write_parquet(&second, schema(), &[batch(vec![-1], vec![Some("AT")])]).unwrap();
+
+        let parts = vec![first, second];
+        let dataset = Dataset::from_parts("t".to_string(), dir.path().to_path_buf(), parts);
+        let stats = table_stats(&dataset).unwrap();
+        assert_eq!(stats["num_rows"], 4);
+        let id = &stats["columns"][0];
+        assert_eq!((&id["min"], &id["max"]), (&json!(-1), &json!(9)));
+        assert_eq!(id["null_count"], 0);
+        let country = &stats["columns"][1];
+        assert_eq!(
+            (&country["min"], &country["max"]),
+            (&json!("AT"), &json!("IT"))
+        );
+        assert_eq!(country["null_count"], 2);
+        // ArrowWriter records no distinct counts
+        assert_eq!(country["distinct_count"], Value::Null);
+    }
+
+    /// Writes a Parquet file with a required Int32 `id` column and one row group per group of
+    /// values, each recording its number of distinct values in the footer.
+    fn write_with_distinct_counts(path: &Path, groups: &[&[i32]]) {
+        let message = parse_message_type("message schema { REQUIRED INT32 id; }").unwrap();
+        let props = Arc::new(WriterProperties::builder().build());
+        let file = File::create(path).unwrap();
+        let mut writer = SerializedFileWriter::new(file, Arc::new(message), props).unwrap();
+        for values in groups {
+            let distinct: HashSet<&i32> = values.iter().collect();
+            let mut group = writer.next_row_group().unwrap();
+            let mut column = group.next_column().unwrap().unwrap();
+            let (min, max) = (values.iter().min(), values.iter().max());
+            column
+                .typed::<Int32Type>()
+                .write_batch_with_statistics(
+                    values,
+                    None,
+                    None,
+                    min,
+                    max,
+                    Some(distinct.len() as u64),
+                )
+                .unwrap();
+            column.close().unwrap();
+            group.close().unwrap();
+        }
+        writer.close().unwrap();
+    }
+
+    #[test]
+    fn test_parquet_distinct_counts() {
+        let dir = tempfile::tempdir().unwrap();
+        let single = dir.path().join("single.parquet");
+        write_with_distinct_counts(&single, &[&[1, 2, 2, 3]]);
+        let dataset = Dataset::from_parts("t".to_string(), single.clone(), vec![single]);
+        let id = &table_stats(&dataset).unwrap()["columns"][0];
+        assert_eq!(id["distinct_count"], 3);
+        assert_eq!(id["distinct_count_exact"], true);
+
+        // Row groups may share values, so their counts add up to an upper bound
+        let groups = dir.path().join("groups.parquet");
+        write_with_distinct_counts(&groups, &[&[1, 2], &[2, 3, 3]]);
+        let dataset = Dataset::from_parts("t".to_string(), groups.clone(), vec![groups]);
+        let id = &table_stats(&dataset).unwrap()["columns"][0];
+        assert_eq!(id["distinct_count"], 4);
+        assert_eq!(id["distinct_count_exact"], false);
+    }
+
+    #[test]
+    fn test_distinct_count_is_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.arrow");
+        let ids: Vec<i32> = (0..=MAX_DISTINCT_VALUES as i32).collect();
+        let countries = vec![Some("DE"); ids.len()];
+        write_arrow(&path, schema(), &[batch(ids, countries)]).unwrap();
+
+        let dataset = Dataset::from_parts("t".to_string(), path.clone(), vec![path]);
+        let stats = table_stats(&dataset).unwrap();
+        assert_eq!(stats["columns"][0]["distinct_count"], Value::Null);
+        assert_eq!(stats["columns"][1]["distinct_count"], 1);
+    }
+}
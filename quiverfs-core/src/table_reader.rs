@@ -0,0 +1,182 @@
+use crate::common_schema::FileFormat;
+use arrow::datatypes::SchemaRef;
+use arrow::ipc::reader::FileReader;
+use arrow::record_batch::RecordBatch;
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+fn detect_format(path: &Path) -> Result<FileFormat, String> {
+    FileFormat::from_path(path)
+        .ok_or_else(|| format!("Unsupported file extension: {}", path.display()))
+}
+
+/// Checks whether a data file has been completely written.
+///
+/// Arrow IPC and Parquet files begin and end with the same magic bytes, and writers only add
+/// the trailing copy once the footer is in place, so a file that is still being written fails
+/// this check. So do Feather v1 files, which predate the Arrow IPC format and cannot be read;
+/// [`is_feather_v1`] tells them apart.
+pub fn has_complete_footer<P: AsRef<Path>>(path: P) -> bool {
+    let path = path.as_ref();
+    let magic: &[u8] = match FileFormat::from_path(path) {
+        Some(FileFormat::Arrow) => b"ARROW1",
+        Some(FileFormat::Parquet) => b"PAR1",
+        None => return false,
+    };
+    let Ok(mut file) = File::open(path) else {
+        return false;
+    };
+    let mut head = vec![0; magic.len()];
+    let mut tail = vec![0; magic.len()];
+    file.read_exact(&mut head).is_ok()
+        && file.seek(SeekFrom::End(-(magic.len() as i64))).is_ok()
+        && file.read_exact(&mut tail).is_ok()
+        && head == magic
+        && tail == magic
+}
+
+/// Checks whether a file is in the Feather v1 format, which starts with `FEA1` rather than
+/// the `ARROW1` of Arrow IPC files, also known as Feather v2.
+pub fn is_feather_v1<P: AsRef<Path>>(path: P) -> bool {
+    let mut head = [0; 4];
+    File::open(path)
+        .and_then(|mut file| file.read_exact(&mut head))
+        .is_ok_and(|_| &head == b"FEA1")
+}
+
+/// Reads the Arrow schema of an Arrow IPC or Parquet file without decoding any rows.
+pub fn read_schema<P: AsRef<Path>>(path: P) -> Result<SchemaRef, String> {
+    let path = path.as_ref();
+    let format = detect_format(path)?;
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    match format {
+        FileFormat::Arrow => FileReader::try_new(BufReader::new(file), None)
+            .map(|reader| reader.schema())
+            .map_err(|e| format!("Failed to read Arrow IPC file: {}", e)),
+        FileFormat::Parquet => ParquetRecordBatchReaderBuilder::try_new(file)
+            .map(|builder| builder.schema().clone())
+            .map_err(|e| format!("Failed to read Parquet file: {}", e)),
+    }
+}
+
+/// Reads every record batch of an Arrow IPC or Parquet file, along with its schema.
+pub fn read_record_batches<P: AsRef<Path>>(
+    path: P,
+) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+    let path = path.as_ref();
+    let format = detect_format(path)?;
+    let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+    match format {
+        FileFormat::Arrow => {
+            let reader = FileReader::try_new(BufReader::new(file), None)
+                .map_err(|e| format!("Failed to read Arrow IPC file: {}", e))?;
+            let schema = reader.schema();
+            let batches = reader
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read Arrow IPC batch: {}", e))?;
+            Ok((schema, batches))
+        }
+        FileFormat::Parquet => {
+            let builder = ParquetRecordBatchReaderBuilder::try_new(file)
+                .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+            let schema = builder.schema().clone();
+            let reader = builder
+                .build()
+                .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+            let batches = reader
+                .collect::<Result<Vec<_>, _>>()
+                .map_err(|e| format!("Failed to read Parquet batch: {}", e))?;
+            Ok((schema, batches))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, StringArray};
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::ipc::writer::FileWriter;
+    use parquet::arrow::ArrowWriter;
+    use std::sync::Arc;
+
+    fn sample_batch() -> RecordBatch {
+        let schema = Arc::new(Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, true),
+        ]));
+        RecordBatch::try_new(
+            schema,
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2, 3])),
+                Arc::new(StringArray::from(vec![Some("a"), None, Some("c")])),
+            ],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_read_record_batches_arrow() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.arrow");
+        let batch = sample_batch();
+        let mut writer =
+            FileWriter::try_new(File::create(&path).unwrap(), &batch.schema()).unwrap();
+        writer.write(&batch).unwrap();
+        writer.finish().unwrap();
+
+        let (schema, batches) = read_record_batches(&path).unwrap();
+        assert_eq!(schema.field(0).name(), "id");
+        assert_eq!(batches.len(), 1);
+        assert_eq!(batches[0].num_rows(), 3);
+        assert_eq!(read_schema(&path).unwrap(), schema);
+    }
+
+    #[test]
+    fn test_read_record_batches_parquet() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.parquet");
+        let batch = sample_batch();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.close().unwrap();
+
+        let (schema, batches) = read_record_batches(&path).unwrap();
+        assert_eq!(schema.field(1).name(), "name");
+        let rows: usize = batches.iter().map(|b| b.num_rows()).sum();
+        assert_eq!(rows, 3);
+    }
+
+    #[test]
+    fn test_has_complete_footer() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("t.parquet");
+        let batch = sample_batch();
+        let mut writer =
+            ArrowWriter::try_new(File::create(&path).unwrap(), batch.schema(), None).unwrap();
+        writer.write(&batch).unwrap();
+        writer.flush().unwrap();
+        assert!(!has_complete_footer(&path));
+        writer.close().unwrap();
+        assert!(has_complete_footer(&path));
+
+        let truncated = dir.path().join("truncated.arrow");
+        std::fs::write(&truncated, b"ARROW1\0\0").unwrap();
+        assert!(!has_complete_footer(&truncated));
+        assert!(!is_feather_v1(&truncated));
+
+        let feather = dir.path().join("old.feather");
+        std::fs::write(&feather, b"FEA1\0\0\0\0FEA1").unwrap();
+        assert!(!has_complete_footer(&feather));
+        assert!(is_feather_v1(&feather));
+    }
+
+    #[test]
+    fn test_read_record_batches_unsupported_extension() {
+        let result = read_record_batches("data.csv");
+        assert!(result.unwrap_err().contains("Unsupported file extension"));
+    }
+}
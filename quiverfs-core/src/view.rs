@@ -0,0 +1,183 @@
+use arrow::compute::{CastOptions, cast_with_options};
+use arrow::datatypes::{DataType, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use std::collections::{BTreeMap, BTreeSet};
+use std::sync::Arc;
+
+/// Which columns of a table are shown and how: columns can be hidden, renamed and converted
+/// to another type. Columns are always named as in the table's files.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnView {
+    pub hide: BTreeSet<String>,
+    /// New names of columns, by their name in the files.
+    pub rename: BTreeMap<String, String>,
+    /// Types to convert columns to, by their name in the files.
+    pub cast: BTreeMap<String, DataType>,
+}
+
+impl ColumnView {
+    /// Describes the columns named by the view that `schema` lacks, and the renamed columns
+    /// whose new name is already taken.
+    pub fn check(&self, schema: &Schema) -> Vec<String> {
+        let mut problems = Vec::new();
+        let columns = self
+            .hide
+            .iter()
+            .chain(self.rename.keys())
+            .chain(self.cast.keys());
+        for column in columns.collect::<BTreeSet<_>>() {
+            if schema.field_with_name(column).is_err() {
+                problems.push(format!("has no column '{}'", column));
+            }
+        }
+        let mut taken = BTreeSet::new();
+        for field in self.schema(schema).fields() {
+            if !taken.insert(field.name()) {
+                problems.push(format!("has more than one column named '{}'", field.name()));
+            }
+        }
+        problems
+    }
+
+    /// Returns the schema of the rows once the view is applied to rows with `schema`.
+    pub fn schema(&self, schema: &Schema) -> SchemaRef {
+        let fields: Vec<_> = schema
+            .fields()
+            .iter()
+            .filter(|field| !self.hide.contains(field.name()))
+            .map(|field| {
+                let mut field = field.as_ref().clone();
+                if let Some(data_type) = self.cast.get(field.name()) {
+                    field = field.with_data_type(data_type.clone());
+                }
+                if let Some(name) = self.rename.get(field.name()) {
+                    field = field.with_name(name);
+                }
+                field
+            })
+            .collect();
+        Arc::new(Schema::new_with_metadata(fields, schema.metadata().clone()))
+    }
+
+    /// Applies the view to `batches` of rows with `schema`. Values that cannot be converted to
+    /// their new type are an error rather than being read as null.
+    pub fn apply(
+        &self,
+        schema: &Schema,
+        batches: &[RecordBatch],
+    ) -> Result<(SchemaRef, Vec<RecordBatch>), String> {
+        let output = self.schema(schema);
+        let options = CastOptions {
+            safe: false,
+            ..CastOptions::default()
+        };
+        let mut applied = Vec::new();
+        for batch in batches {
+            let mut columns = Vec::new();
+            for (field, column) in schema.fields().iter().zip(batch.columns()) {
+                if self.hide.contains(field.name()) {
+                    continue;
+                }
+                columns.push(match self.cast.get(field.name()) {
+                    Some(data_type) => {
+                        cast_with_options(column, data_type, &options).map_err(|e| {
+                            format!(
+                                "Failed to convert column '{}' to {}: {}",
+                                field.name(),
+                                data_type,
+                                e
+                            )
+                        })?
+                    }
+                    None => column.clone(),
+                });
+            }
+            let batch = RecordBatch::try_new(output.clone(), columns)
+                .map_err(|e| format!("Failed to apply column view: {}", e))?;
+            applied.push(batch);
+        }
+        Ok((output, applied))
+    }
+
+    /// Returns the name in the table's files of the column shown as `name`.
+    pub fn source_column<'a>(&'a self, name: &'a str) -> &'a str {
+        self.rename
+            .iter()
+            .find(|(_, new)| *new == name)
+            .map_or(name, |(old, _)| old.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::{Int32Array, Int64Array, StringArray};
+    use arrow::datatypes::Field;
+
+    #[test]
+    fn test_apply_hides_renames_and_casts() {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("secret", DataType::Utf8, true),
+            Field::new("amt", DataType::Utf8, true),
+        ]);
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![
+                Arc::new(Int32Array::from(vec![1, 2])),
+                Arc::new(StringArray::from(vec!["x", "y"])),
+                Arc::new(StringArray::from(vec![Some("10"), None])),
+            ],
+        )
+        .unwrap();
+        let view = ColumnView {
+            hide: BTreeSet::from(["secret".to_string()]),
+            rename: BTreeMap::from([("amt".to_string(), "amount".to_string())]),
+            cast: BTreeMap::from([("amt".to_string(), DataType::Int64)]),
+        };
+        assert!(view.check(&schema).is_empty());
+
+        let (output, batches) = view.apply(&schema, &[batch]).unwrap();
+        let names: Vec<_> = output.fields().iter().map(|f| f.name().as_str()).collect();
+        assert_eq!(names, ["id", "amount"]);
+        let amounts = batches[0]
+            .column(1)
+            .as_any()
+            .downcast_ref::<Int64Array>()
+            .unwrap();
+        assert_eq!(amounts.iter().collect::<Vec<_>>(), [Some(10), None]);
+        assert_eq!(view.source_column("amount"), "amt");
+        assert_eq!(view.source_column("id"), "id");
+    }
+
+    #[test]
+    fn test_check_and_failed_casts() {
+        let schema = Schema::new(vec![
+            Field::new("id", DataType::Int32, false),
+            Field::new("name", DataType::Utf8, false),
+        ]);
+        let view = ColumnView {
+            hide: BTreeSet::from(["missing".to_string()]),
+            rename: BTreeMap::from([("name".to_string(), "id".to_string())]),
+            cast: BTreeMap::from([("name".to_string(), DataType::Int64)]),
+        };
+        assert_eq!(
+            view.check(&schema),
+            [
+                "has no column 'missing'",
+                "has more than one column named 'id'"
+            ]
+        );
+
+        let batch = RecordBatch::try_new(
+            Arc::new(schema.clone()),
+            vec![
+                Arc::new(Int32Array::from(vec![1])),
+                Arc::new(StringArray::from(vec!["ann"])),
+            ],
+        )
+        .unwrap();
+        let error = view.apply(&schema, &[batch]).unwrap_err();
+        assert!(error.starts_with("Failed to convert column 'name' to Int64"));
+    }
+}
@@ -0,0 +1,112 @@
+use crate::common_schema::FileFormat;
+use crate::dataset::Dataset;
+use crate::read_schema;
+use crate::rows::chunk_row_counts;
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+/// Prefix of the attributes that describe a table.
+const PREFIX: &str = "user.quiver.";
+
+/// Prefix of the attributes that hold the key-value metadata of a table's first part.
+const METADATA_PREFIX: &str = "user.quiver.metadata.";
+
+/// Reads the schema-level metadata of an Arrow IPC or Parquet file. Parquet files also list
+/// their key-value metadata as stored in the footer, including keys such as `ARROW:schema`
+/// that are not part of the Arrow schema. Parquet keys without a value are left out.
+fn file_metadata(path: &Path, format: &FileFormat) -> Result<BTreeMap<String, String>, String> {
+    let mut metadata: BTreeMap<_, _> = read_schema(path)?.metadata().clone().into_iter().collect();
+    if *format == FileFormat::Parquet {
+        let file = File::open(path).map_err(|e| format!("Failed to open file: {}", e))?;
+        let reader = SerializedFileReader::new(file)
+            .map_err(|e| format!("Failed to read Parquet file: {}", e))?;
+        let key_values = reader.metadata().file_metadata().key_value_metadata();
+        for kv in key_values.into_iter().flatten() {
+            if let Some(value) = &kv.value {
+                metadata.insert(kv.key.clone(), value.clone());
+            }
+        }
+    }
+    Ok(metadata)
+}
+
+/// Describes a table as extended attributes, by name.
+///
+/// Every table has `user.quiver.format`, `user.quiver.num_rows` and `user.quiver.schema`, the
+/// latter holding the same JSON as its `schema.json` on one line. The metadata stored in the
+/// first part, such as the `pandas` or `ARROW:schema` keys, follows under
+/// `user.quiver.metadata.<key>`. Row counts are read from the footers of every part.
+pub fn table_attributes(dataset: &Dataset) -> Result<BTreeMap<String, String>, String> {
+    let first = dataset
+        .parts
+        .first()
+        .ok_or_else(|| format!("Dataset '{}' has no readable parts", dataset.name))?;
+    let format = FileFormat::from_path(first)
+        .ok_or_else(|| format!("Unsupported file extension: {}", first.display()))?;
+    let format_name = match format {
+        FileFormat::Arrow => "arrow",
+        FileFormat::Parquet => "parquet",
+    };
+    let schema = dataset.schema_json()?;
+    let mut num_rows = 0;
+    for part in &dataset.parts {
+        num_rows += chunk_row_counts(part)?.iter().sum::<usize>();
+    }
+
+    let mut attributes = BTreeMap::new();
+    attributes.insert(format!("{PREFIX}format"), format_name.to_string());
+    attributes.insert(format!("{PREFIX}num_rows"), num_rows.to_string());
+    attributes.insert(format!("{PREFIX}schema"), schema.to_string());
+    for (key, value) in file_metadata(first, &format)? {
+        attributes.insert(format!("{METADATA_PREFIX}{key}"), value);
+    }
+    Ok(attributes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use arrow::array::Int32Array;
+    use arrow::datatypes::{DataType, Field, Schema};
+    use arrow::record_batch::RecordBatch;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    fn batch() -> RecordBatch {
+        let metadata = HashMap::from([("origin".to_string(), "test".to_string())]);
+        let schema =
+            Schema::new(vec![Field::new("id", DataType::Int32, false)]).with_metadata(metadata);
+        RecordBatch::try_new(
+            Arc::new(schema),
+            vec![Arc::new(Int32Array::from(vec![1, 2, 3]))],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_table_attributes() {
+        let dir = tempfile::tempdir().unwrap();
+        let arrow_path = dir.path().join("t.arrow");
+        let parquet_path = dir.path().join("t.parquet");
+        crate::ingest::write_arrow(&arrow_path, batch().schema(), &[batch(), batch()]).unwrap();
+        crate::ingest::write_parquet(&parquet_path, batch().schema(), &[batch()]).unwrap();
+
+        let dataset = Dataset::from_parts("t".to_string(), arrow_path.clone(), vec![arrow_path]);
+        let attributes = table_attributes(&dataset).unwrap();
+        assert_eq!(attributes["user.quiver.format"], "arrow");
+        assert_eq!(attributes["user.quiver.num_rows"], "6");
+        assert!(attributes["user.quiver.schema"].contains("\"id\""));
+        assert_eq!(attributes["user.quiver.metadata.origin"], "test");
+
+        let dataset =
+            Dataset::from_parts("t".to_string(), parquet_path.clone(), vec![parquet_path]);
+        let attributes = table_attributes(&dataset).unwrap();
+        assert_eq!(attributes["user.quiver.format"], "parquet");
+        assert_eq!(attributes["user.quiver.num_rows"], "3");
+        assert_eq!(attributes["user.quiver.metadata.origin"], "test");
+        // The Arrow writer stores the Arrow schema alongside the Parquet schema
+        assert!(attributes.contains_key("user.quiver.metadata.ARROW:schema"));
+    }
+}
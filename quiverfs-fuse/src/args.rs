@@ -0,0 +1,397 @@
+use crate::fs::{CollisionPolicy, ConflictPolicy, Layout, OwnerOverride, Source};
+use clap::Args;
+use fuser::MountOption;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Command-line arguments for mounting, shared by `quiverfs-fuse` and `quiverfs-cli mount`.
+#[derive(Args, Debug)]
+pub struct MountArgs {
+    /// Directory holding the Arrow and Parquet files, unless sources are given with --source
+    #[arg(required_unless_present = "sources")]
+    pub source: Option<PathBuf>,
+    /// Empty directory to mount the filesystem on
+    #[arg(required_unless_present = "sources")]
+    pub mountpoint: Option<PathBuf>,
+    /// Mount the directory PATH as the top-level directory NAME. Repeat to mount several
+    #[arg(long = "source", value_name = "NAME=PATH")]
+    pub sources: Vec<Source>,
+    /// Merge the sources given with --source into one tree instead, resolving tables found in
+    /// several of them with POLICY: first, suffix or error [default: first]
+    #[arg(
+        long,
+        value_name = "POLICY",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "first",
+        requires = "sources"
+    )]
+    pub overlay: Option<ConflictPolicy>,
+    /// Allow users other than the one mounting to access the filesystem
+    #[arg(long)]
+    pub allow_other: bool,
+    /// Unmount automatically when the process exits
+    #[arg(long)]
+    pub auto_unmount: bool,
+    /// Stay attached to the terminal until unmounted (the default), even if `-o daemon` is given
+    #[arg(short, long, conflicts_with = "daemon")]
+    pub foreground: bool,
+    /// Detach from the terminal once the filesystem is mounted
+    #[arg(short, long)]
+    pub daemon: bool,
+    /// Report every entry as owned by this user id
+    #[arg(long)]
+    pub uid: Option<u32>,
+    /// Report every entry as owned by this group id
+    #[arg(long)]
+    pub gid: Option<u32>,
+    /// Seconds the kernel may cache attributes and directory entries [default: 1]
+    #[arg(long, value_name = "SECONDS", value_parser = parse_ttl)]
+    pub ttl: Option<Duration>,
+    /// Allow CSV and JSON files to be copied in and rows to be appended to tables
+    #[arg(long, visible_alias = "rw")]
+    pub read_write: bool,
+    /// How to name tables whose names collide: suffix, extension or error [default: suffix]
+    #[arg(long, value_name = "POLICY")]
+    pub collisions: Option<CollisionPolicy>,
+    /// Write the id of the serving process to this file, and remove it once unmounted
+    #[arg(long, value_name = "PATH")]
+    pub pidfile: Option<PathBuf>,
+    /// TOML file declaring tables made of the files matching patterns
+    #[arg(long, value_name = "PATH")]
+    pub config: Option<PathBuf>,
+    /// Comma-separated mount options, as in fstab. Options given as flags take precedence
+    #[arg(short = 'o', value_name = "OPTIONS")]
+    pub options: Vec<String>,
+}
+
+/// Everything needed to mount, combined from the flags and the `-o` options.
+#[derive(Debug, Default)]
+pub(crate) struct MountConfig {
+    pub sources: Vec<Source>,
+    pub layout: Layout,
+    pub mountpoint: PathBuf,
+    pub owner: OwnerOverride,
+    pub read_write: bool,
+    pub ttl: Option<Duration>,
+    pub collisions: CollisionPolicy,
+    pub daemon: bool,
+    pub pidfile: Option<PathBuf>,
+    pub config: Option<PathBuf>,
+    /// Options passed on to the kernel, apart from `ro` and `rw`.
+    pub fuse_options: Vec<MountOption>,
+}
+
+impl MountConfig {
+    /// Returns the options to mount with, including whether the mount is writable.
+    pub fn mount_options(&self) -> Vec<MountOption> {
+        let access = if self.read_write {
+            MountOption::RW
+        } else {
+            MountOption::RO
+        };
+        let mut options = vec![access];
+        for option in &self.fuse_options {
+            if !options.contains(option) {
+                options.push(option.clone());
+            }
+        }
+        options
+    }
+
+    /// Checks that the options can be used together.
+    fn check(&self) -> Result<(), String> {
+        // The kernel only lets the helper that unmounts on exit do so when others may access
+        // the mount
+        let has = |option| self.fuse_options.contains(&option);
+        if has(MountOption::AutoUnmount)
+            && !has(MountOption::AllowOther)
+            && !has(MountOption::AllowRoot)
+        {
+            return Err("auto_unmount needs allow_other or allow_root".to_string());
+        }
+        Ok(())
+    }
+
+    /// Applies one `key` or `key=value` option given with `-o`.
+    fn apply(&mut self, option: &str) -> Result<(), String> {
+        let parse_id = |value: &str| {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid value for {option}: expected a numeric id"))
+        };
+        match option.split_once('=') {
+            Some(("uid", value)) => self.owner.uid = Some(parse_id(value)?),
+            Some(("gid", value)) => self.owner.gid = Some(parse_id(value)?),
+            Some(("ttl", value)) => self.ttl = Some(parse_ttl(value)?),
+            Some(("collisions", value)) => self.collisions = value.parse()?,
+            Some(("pidfile", value)) => self.pidfile = Some(PathBuf::from(value)),
+            Some(("config", value)) => self.config = Some(PathBuf::from(value)),
+            Some(("fsname", value)) => self
+                .fuse_options
+                .push(MountOption::FSName(value.to_string())),
+            Some(("subtype", value)) => self
+                .fuse_options
+                .push(MountOption::Subtype(value.to_string())),
+            Some(_) => return Err(format!("Unknown mount option: {option}")),
+            None => match option {
+                "rw" => self.read_write = true,
+                "ro" => self.read_write = false,
+                "daemon" => self.daemon = true,
+                "foreground" => self.daemon = false,
+                // Options used by mount and fstab themselves, which mean nothing to the filesystem
+                "" | "defaults" | "auto" | "noauto" | "user" | "nouser" | "users" | "_netdev"
+                | "nofail" => {}
+                _ => self.fuse_options.push(fuse_flag(option)?),
+            },
+        }
+        Ok(())
+    }
+}
+
+/// Maps a flag given with `-o` to the kernel mount option of the same name.
+fn fuse_flag(option: &str) -> Result<MountOption, String> {
+    Ok(match option {
+        "allow_other" => MountOption::AllowOther,
+        "allow_root" => MountOption::AllowRoot,
+        "auto_unmount" => MountOption::AutoUnmount,
+        "default_permissions" => MountOption::DefaultPermissions,
+        "dev" => MountOption::Dev,
+        "nodev" => MountOption::NoDev,
+        "suid" => MountOption::Suid,
+        "nosuid" => MountOption::NoSuid,
+        "exec" => MountOption::Exec,
+        "noexec" => MountOption::NoExec,
+        "atime" => MountOption::Atime,
+        "noatime" => MountOption::NoAtime,
+        "dirsync" => MountOption::DirSync,
+        "sync" => MountOption::Sync,
+        "async" => MountOption::Async,
+        _ => return Err(format!("Unknown mount option: {option}")),
+    })
+}
+
+/// Parses a TTL given in seconds, such as `1` or `0.5`.
+fn parse_ttl(value: &str) -> Result<Duration, String> {
+    value
+        .parse::<f64>()
+        .ok()
+        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+        .ok_or_else(|| format!("Invalid TTL '{value}': expected a number of seconds"))
+}
+
+impl MountArgs {
+    /// Combines the `-o` options with the flags, which take precedence.
+    pub(crate) fn config(&self) -> Result<MountConfig, String> {
+        let mut config = MountConfig::default();
+        (config.sources, config.layout, config.mountpoint) = self.sources()?;
+        for option in self.options.iter().flat_map(|options| options.split(',')) {
+            config.apply(option)?;
+        }
+        config.read_write |= self.read_write;
+        if self.daemon || self.foreground {
+            config.daemon = self.daemon;
+        }
+        config.owner.uid = self.uid.or(config.owner.uid);
+        config.owner.gid = self.gid.or(config.owner.gid);
+        config.ttl = self.ttl.or(config.ttl);
+        config.pidfile = self.pidfile.clone().or(config.pidfile);
+        config.config = self.config.clone().or(config.config);
+        if let Some(collisions) = self.collisions {
+            config.collisions = collisions;
+        }
+        if self.allow_other {
+            config.fuse_options.push(MountOption::AllowOther);
+        }
+        if self.auto_unmount {
+            config.fuse_options.push(MountOption::AutoUnmount);
+        }
+        config.check()?;
+        Ok(config)
+    }
+
+    /// Returns the sources, how they are combined and the mountpoint. Sources given with
+    /// `--source` leave a single positional argument, which is the mountpoint.
+    fn sources(&self) -> Result<(Vec<Source>, Layout, PathBuf), String> {
+        if self.sources.is_empty() {
+            let (Some(source), Some(mountpoint)) = (&self.source, &self.mountpoint) else {
+                return Err("Expected a source directory and a mountpoint".to_string());
+            };
+            let source = Source {
+                name: String::new(),
+                path: source.clone(),
+            };
+            return Ok((vec![source], Layout::default(), mountpoint.clone()));
+        }
+        let mountpoint = match (&self.source, &self.mountpoint) {
+            (Some(mountpoint), None) => mountpoint.clone(),
+            (None, _) => return Err("Expected a mountpoint".to_string()),
+            (Some(_), Some(_)) => {
+                return Err(
+                    "A source directory cannot be given both as an argument and with --source"
+                        .to_string(),
+                );
+            }
+        };
+        for (i, source) in self.sources.iter().enumerate() {
+            if self.sources[..i]
+                .iter()
+                .any(|other| other.name == source.name)
+            {
+                return Err(format!(
+                    "Source name '{}' is given more than once",
+                    source.name
+                ));
+            }
+        }
+        let layout = match self.overlay {
+            Some(policy) => Layout::Overlay(policy),
+            None => Layout::Namespaces,
+        };
+        Ok((self.sources.clone(), layout, mountpoint))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        mount: MountArgs,
+    }
+
+    fn parse(args: &[&str]) -> Result<MountConfig, String> {
+        let args = ["quiverfs-fuse"].iter().chain(args);
+        let cli = Cli::try_parse_from(args).map_err(|e| e.to_string())?;
+        cli.mount.config()
+    }
+
+    #[test]
+    fn test_config_from_flags() {
+        let config = parse(&[
+            "--rw",
+            "--uid",
+            "7",
+            "--ttl",
+            "0.5",
+            "-d",
+            "--pidfile",
+            "q.pid",
+            "src",
+            "mnt",
+        ])
+        .unwrap();
+        assert_eq!(config.sources[0].path, PathBuf::from("src"));
+        assert_eq!(config.mountpoint, PathBuf::from("mnt"));
+        assert!(config.read_write);
+        assert_eq!((config.owner.uid, config.owner.gid), (Some(7), None));
+        assert_eq!(config.ttl, Some(Duration::from_millis(500)));
+        assert!(config.daemon);
+        assert_eq!(config.pidfile, Some(PathBuf::from("q.pid")));
+        assert_eq!(config.mount_options(), [MountOption::RW]);
+    }
+
+    #[test]
+    fn test_options_are_split_and_overridden_by_flags() {
+        let config = parse(&[
+            "-o",
+            "ro,uid=1,gid=2,ttl=5,daemon",
+            "-o",
+            "collisions=error,nosuid,defaults,fsname=q",
+            "--uid",
+            "3",
+            "--ttl",
+            "2",
+            "--collisions",
+            "extension",
+            "--foreground",
+            "src",
+            "mnt",
+        ])
+        .unwrap();
+        assert_eq!((config.owner.uid, config.owner.gid), (Some(3), Some(2)));
+        assert_eq!(config.ttl, Some(Duration::from_secs(2)));
+        assert_eq!(config.collisions, CollisionPolicy::Extension);
+        assert!(!config.daemon);
+        assert_eq!(
+            config.mount_options(),
+            [
+                MountOption::RO,
+                MountOption::NoSuid,
+                MountOption::FSName("q".to_string()),
+            ]
+        );
+
+        let config = parse(&["-o", "ro,daemon", "--rw", "src", "mnt"]).unwrap();
+        assert!(config.read_write);
+        assert!(config.daemon);
+    }
+
+    #[test]
+    fn test_rejected_options() {
+        for args in [
+            &["-o", "nosuchoption", "src", "mnt"][..],
+            &["-o", "color=blue", "src", "mnt"],
+            &["-o", "uid=me", "src", "mnt"],
+            &["-o", "ttl=-1", "src", "mnt"],
+            &["-o", "collisions=random", "src", "mnt"],
+            &["--auto-unmount", "src", "mnt"],
+            &["-o", "auto_unmount", "src", "mnt"],
+            &["--foreground", "--daemon", "src", "mnt"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?}");
+        }
+        assert!(parse(&["--auto-unmount", "--allow-other", "src", "mnt"]).is_ok());
+        assert!(parse(&["-o", "auto_unmount,allow_root", "src", "mnt"]).is_ok());
+    }
+
+    #[test]
+    fn test_fuse_flags() {
+        for (flag, option) in [
+            ("allow_other", MountOption::AllowOther),
+            ("allow_root", MountOption::AllowRoot),
+            ("auto_unmount", MountOption::AutoUnmount),
+            ("default_permissions", MountOption::DefaultPermissions),
+            ("dev", MountOption::Dev),
+            ("nodev", MountOption::NoDev),
+            ("suid", MountOption::Suid),
+            ("nosuid", MountOption::NoSuid),
+            ("exec", MountOption::Exec),
+            ("noexec", MountOption::NoExec),
+            ("atime", MountOption::Atime),
+            ("noatime", MountOption::NoAtime),
+            ("dirsync", MountOption::DirSync),
+            ("sync", MountOption::Sync),
+            ("async", MountOption::Async),
+        ] {
+            assert_eq!(fuse_flag(flag), Ok(option));
+        }
+        assert!(fuse_flag("ro").is_err());
+    }
+
+    #[test]
+    fn test_parse_ttl() {
+        assert_eq!(parse_ttl("1"), Ok(Duration::from_secs(1)));
+        assert_eq!(parse_ttl("0.25"), Ok(Duration::from_millis(250)));
+        assert_eq!(parse_ttl("0"), Ok(Duration::ZERO));
+        for invalid in ["", "-1", "soon", "inf", "NaN"] {
+            assert!(parse_ttl(invalid).is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn test_sources() {
+        let config = parse(&["--source", "a=/x", "--source", "b=/y", "mnt"]).unwrap();
+        assert_eq!(config.layout, Layout::Namespaces);
+        assert_eq!(config.mountpoint, PathBuf::from("mnt"));
+        let config = parse(&["--source", "a=/x", "--overlay", "mnt"]).unwrap();
+        assert_eq!(config.layout, Layout::Overlay(ConflictPolicy::First));
+
+        assert!(parse(&["--source", "a=/x", "--source", "a=/y", "mnt"]).is_err());
+        assert!(parse(&["--source", "a=/x", "src", "mnt"]).is_err());
+        assert!(parse(&["--overlay", "src", "mnt"]).is_err());
+    }
+}
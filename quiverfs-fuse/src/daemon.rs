@@ -0,0 +1,238 @@
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::os::fd::{AsRawFd, FromRawFd};
+use std::os::unix::net::UnixDatagram;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// The write end of a pipe to the parent process, which waits for the daemon to report
+/// whether the filesystem could be mounted.
+pub struct Readiness {
+    pipe: File,
+}
+
+impl Readiness {
+    /// Tells the parent that the filesystem is mounted, letting it exit successfully, and
+    /// detaches from the terminal.
+    pub fn ready(mut self) {
+        let _ = self.pipe.write_all(&[0]);
+        if let Ok(null) = File::options().read(true).write(true).open("/dev/null") {
+            for fd in [libc::STDIN_FILENO, libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+                // SAFETY: both descriptors are open
+                unsafe { libc::dup2(null.as_raw_fd(), fd) };
+            }
+        }
+    }
+
+    /// Sends an error to the parent, which prints it and exits with a failure status.
+    pub fn fail(mut self, message: &str) {
+        let _ = self.pipe.write_all(message.as_bytes());
+    }
+}
+
+/// Forks into the background. Returns in the child, while the parent waits for the child to
+/// report through its [`Readiness`] and exits with the outcome.
+///
+/// Must be called before any other thread is started.
+pub fn daemonize() -> Result<Readiness, String> {
+    let mut fds = [0; 2];
+    // SAFETY: `fds` has room for the two descriptors
+    if unsafe { libc::pipe(fds.as_mut_ptr()) } != 0 {
+        return Err(format!(
+            "Cannot create pipe: {}",
+            io::Error::last_os_error()
+        ));
+    }
+    // SAFETY: the descriptors were just created and nothing else owns them
+    let (mut reader, writer) = unsafe { (File::from_raw_fd(fds[0]), File::from_raw_fd(fds[1])) };
+
+    // SAFETY: no other threads are running, so the child starts in a consistent state
+    match unsafe { libc::fork() } {
+        -1 => Err(format!("Cannot fork: {}", io::Error::last_os_error())),
+        0 => {
+            drop(reader);
+            // Leave the terminal's session so that closing the terminal does not stop the
+            // daemon, and do not keep the working directory busy
+            // SAFETY: setsid and chdir have no memory safety requirements
+            unsafe {
+                libc::setsid();
+                libc::chdir(c"/".as_ptr());
+            }
+            Ok(Readiness { pipe: writer })
+        }
+        _ => {
+            drop(writer);
+            let mut report = Vec::new();
+            let _ = reader.read_to_end(&mut report);
+            match report.as_slice() {
+                [0] => std::process::exit(0),
+                [] => eprintln!("Error: the filesystem exited before it was mounted"),
+                message => eprintln!("Error: {}", String::from_utf8_lossy(message)),
+            }
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The termination signals, blocked in every thread so that they can be waited for.
+pub struct Signals {
+    set: libc::sigset_t,
+}
+
+impl Signals {
+    /// Blocks SIGINT, SIGTERM and SIGHUP in the calling thread and in the threads it starts
+    /// afterwards, so that they no longer kill the process.
+    ///
+    /// Must be called before any other thread is started.
+    pub fn block() -> Result<Signals, String> {
+        // SAFETY: the set is initialized by sigemptyset before it is used
+        unsafe {
+            let mut set = std::mem::zeroed();
+            libc::sigemptyset(&mut set);
+            for signal in [libc::SIGINT, libc::SIGTERM, libc::SIGHUP] {
+                libc::sigaddset(&mut set, signal);
+            }
+            match libc::pthread_sigmask(libc::SIG_BLOCK, &set, std::ptr::null_mut()) {
+                0 => Ok(Signals { set }),
+                errno => Err(format!(
+                    "Cannot block signals: {}",
+                    io::Error::from_raw_os_error(errno)
+                )),
+            }
+        }
+    }
+
+    /// Waits up to `timeout` for one of the signals, returning its name.
+    pub fn wait(&self, timeout: Duration) -> Option<&'static str> {
+        let timeout = libc::timespec {
+            tv_sec: timeout.as_secs() as libc::time_t,
+            tv_nsec: timeout.subsec_nanos() as libc::c_long,
+        };
+        // SAFETY: the set was initialized in `block` and the info pointer may be null
+        match unsafe { libc::sigtimedwait(&self.set, std::ptr::null_mut(), &timeout) } {
+            libc::SIGINT => Some("SIGINT"),
+            libc::SIGTERM => Some("SIGTERM"),
+            libc::SIGHUP => Some("SIGHUP"),
+            _ => None,
+        }
+    }
+}
+
+/// A file holding the id of the running process, removed when dropped.
+pub struct PidFile {
+    path: PathBuf,
+}
+
+impl PidFile {
+    pub fn create(path: &Path) -> Result<PidFile, String> {
+        std::fs::write(path, format!("{}\n", std::process::id()))
+            .map_err(|e| format!("Cannot write pid file {}: {e}", path.display()))?;
+        Ok(PidFile {
+            path: path.to_path_buf(),
+        })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// Reports the state of the service to the service manager, as `sd_notify` does, when
+/// `NOTIFY_SOCKET` names a socket to report to. Failures are ignored.
+pub fn notify(state: &str) {
+    if let Some(socket_path) = std::env::var_os("NOTIFY_SOCKET") {
+        notify_socket(&socket_path, state);
+    }
+}
+
+/// Sends `state` to the service manager listening on `socket_path`.
+fn notify_socket(socket_path: &OsStr, state: &str) {
+    let Ok(socket) = UnixDatagram::unbound() else {
+        return;
+    };
+    let message = format!("{state}\nMAINPID={}", std::process::id());
+    // Names starting with `@` are in the abstract namespace
+    #[cfg(target_os = "linux")]
+    if let Some(name) = socket_path.as_encoded_bytes().strip_prefix(b"@") {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::SocketAddr;
+        if let Ok(address) = SocketAddr::from_abstract_name(name) {
+            let _ = socket.send_to_addr(message.as_bytes(), &address);
+        }
+        return;
+    }
+    let _ = socket.send_to(message.as_bytes(), socket_path);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pid_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("quiverfs.pid");
+        let pidfile = PidFile::create(&path).unwrap();
+        let contents = std::fs::read_to_string(&path).unwrap();
+        assert_eq!(contents, format!("{}\n", std::process::id()));
+        drop(pidfile);
+        assert!(!path.exists());
+
+        let missing = dir.path().join("missing/quiverfs.pid");
+        assert!(
+            PidFile::create(&missing)
+                .err()
+                .unwrap()
+                .starts_with("Cannot write pid file")
+        );
+    }
+
+    fn received(socket: &UnixDatagram) -> String {
+        let mut buffer = [0; 256];
+        socket
+            .set_read_timeout(Some(Duration::from_secs(5)))
+            .unwrap();
+        let len = socket.recv(&mut buffer).unwrap();
+        String::from_utf8_lossy(&buffer[..len]).into_owned()
+    }
+
+    #[test]
+    fn test_notify_socket() {
+        let expected = format!("READY=1\nMAINPID={}", std::process::id());
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("notify");
+        let socket = UnixDatagram::bind(&path).unwrap();
+        notify_socket(path.as_os_str(), "READY=1");
+        assert_eq!(received(&socket), expected);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_notify_abstract_socket() {
+        use std::os::linux::net::SocketAddrExt;
+        use std::os::unix::net::SocketAddr;
+
+        let name = format!("quiverfs-test-{}", std::process::id());
+        let address = SocketAddr::from_abstract_name(name.as_bytes()).unwrap();
+        let socket = UnixDatagram::bind_addr(&address).unwrap();
+        notify_socket(OsStr::new(&format!("@{name}")), "STOPPING=1");
+        assert_eq!(
+            received(&socket),
+            format!("STOPPING=1\nMAINPID={}", std::process::id())
+        );
+    }
+
+    #[test]
+    fn test_signals() {
+        // Signals are blocked in this test's thread only, and raise sends them to it
+        let signals = Signals::block().unwrap();
+        assert_eq!(signals.wait(Duration::from_millis(10)), None);
+        // SAFETY: SIGHUP is blocked, so it stays pending instead of ending the process
+        unsafe { libc::raise(libc::SIGHUP) };
+        assert_eq!(signals.wait(Duration::from_secs(5)), Some("SIGHUP"));
+        assert_eq!(signals.wait(Duration::from_millis(10)), None);
+    }
+}
@@ -1,131 +1,357 @@
-use fuser::{FileAttr, FileType, Filesystem, ReplyAttr, ReplyDirectory, ReplyEntry, Request};
+use fuser::{
+    FileAttr, FileType, Filesystem, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEmpty,
+    ReplyEntry, ReplyOpen, ReplyWrite, ReplyXattr, Request, TimeOrNow,
+};
+use quiverfs_vfs::{
+    CollisionPolicy, Config, ConflictPolicy, EntryKind, Layout, Source, Stat, Tree,
+};
 use std::ffi::OsStr;
 use std::path::PathBuf;
-use std::time::{Duration, UNIX_EPOCH};
-use walkdir::WalkDir;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, SystemTime};
 
-const TTL: Duration = Duration::from_secs(1);
+/// How long the kernel may cache attributes and directory entries by default.
+const DEFAULT_TTL: Duration = Duration::from_secs(1);
 
+/// Owner reported for every entry instead of the owner of its source file.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OwnerOverride {
+    pub uid: Option<u32>,
+    pub gid: Option<u32>,
+}
+
+/// The FUSE adapter around a [`Tree`], which holds all of the filesystem's logic.
 pub struct QuiverFS {
-    source_dir: PathBuf,
-    entries: Vec<(u64, String)>, // (inode, name)
+    tree: Arc<Mutex<Tree>>,
+    owner: OwnerOverride,
+    ttl: Duration,
 }
 
 impl QuiverFS {
     pub fn new(source_dir: PathBuf) -> Self {
-        let mut fs = QuiverFS {
-            source_dir,
-            entries: Vec::new(),
-        };
-        fs.scan_directory();
-        fs
+        QuiverFS {
+            tree: Arc::new(Mutex::new(Tree::new(source_dir))),
+            owner: OwnerOverride::default(),
+            ttl: DEFAULT_TTL,
+        }
     }
 
-    fn scan_directory(&mut self) {
-        let mut inode = 2; // Start at 2 since 1 is root
+    /// Serves several source directories, combined according to `layout`.
+    pub fn with_sources(sources: Vec<Source>, layout: Layout) -> Self {
+        QuiverFS {
+            tree: Arc::new(Mutex::new(Tree::with_sources(sources, layout))),
+            owner: OwnerOverride::default(),
+            ttl: DEFAULT_TTL,
+        }
+    }
+
+    pub fn with_owner(mut self, owner: OwnerOverride) -> Self {
+        self.owner = owner;
+        self
+    }
 
-        for entry in WalkDir::new(&self.source_dir)
-            .min_depth(1)
-            .max_depth(1)
-            .into_iter()
-            .filter_map(Result::ok)
+    /// Sets how long the kernel may cache attributes and directory entries.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Allows CSV and JSON files to be copied into the mount root, where they are converted
+    /// to Parquet tables in the source directory, and rows to be appended to tables.
+    pub fn with_read_write(self, read_write: bool) -> Self {
+        self.lock().set_read_write(read_write);
+        self
+    }
+
+    /// Declares the tables described by a configuration file.
+    pub fn with_config(self, config: Config) -> Self {
+        self.lock().set_config(config);
+        self
+    }
+
+    /// Sets how tables whose names collide are named. Fails with a description of the
+    /// collisions when `policy` is [`CollisionPolicy::Error`] and the source directory has any.
+    pub fn with_collision_policy(self, policy: CollisionPolicy) -> Result<Self, String> {
         {
-            if let Some(ext) = entry.path().extension().and_then(OsStr::to_str) {
-                if ["arrow", "parquet", "feather"].contains(&ext.to_lowercase().as_str()) {
-                    if let Some(name) = entry.path().file_stem().and_then(OsStr::to_str) {
-                        self.entries.push((inode, name.to_string()));
-                        inode += 1;
-                    }
-                }
+            let mut tree = self.lock();
+            tree.set_collision_policy(policy);
+            if policy == CollisionPolicy::Error && !tree.collisions().is_empty() {
+                return Err(tree.collisions().join("\n"));
             }
         }
+        Ok(self)
+    }
+
+    /// Fails with a description of the conflicts when the sources are overlaid with
+    /// [`ConflictPolicy::Error`] and several of them have a table at the same path.
+    pub fn check_conflicts(self) -> Result<Self, String> {
+        {
+            let tree = self.lock();
+            if tree.layout() == Layout::Overlay(ConflictPolicy::Error)
+                && !tree.conflicts().is_empty()
+            {
+                return Err(tree.conflicts().join("\n"));
+            }
+        }
+        Ok(self)
+    }
+
+    /// Returns a handle to the tree so that it can be refreshed while the filesystem is mounted.
+    pub fn tree(&self) -> Arc<Mutex<Tree>> {
+        self.tree.clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Tree> {
+        self.tree.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+fn file_type(kind: EntryKind) -> FileType {
+    match kind {
+        EntryKind::Directory => FileType::Directory,
+        EntryKind::File => FileType::RegularFile,
+    }
+}
+
+fn file_attr(stat: Stat, owner: OwnerOverride) -> FileAttr {
+    let Stat {
+        ino,
+        kind,
+        size,
+        perm,
+        nlink,
+        uid,
+        gid,
+        atime,
+        mtime,
+        ctime,
+        ..
+    } = stat;
+    FileAttr {
+        ino,
+        size,
+        blocks: size.div_ceil(512),
+        atime,
+        mtime,
+        ctime,
+        crtime: ctime,
+        kind: file_type(kind),
+        perm,
+        nlink,
+        uid: owner.uid.unwrap_or(uid),
+        gid: owner.gid.unwrap_or(gid),
+        rdev: 0,
+        flags: 0,
+        blksize: 512,
     }
+}
+
+fn attr(tree: &mut Tree, ino: u64, owner: OwnerOverride) -> Result<FileAttr, libc::c_int> {
+    tree.stat(ino).map(|stat| file_attr(stat, owner))
+}
+
+/// Describes an entry handed to the kernel by a lookup or create, which the kernel keeps a
+/// reference to until it forgets it.
+fn entry(tree: &mut Tree, ino: u64, owner: OwnerOverride) -> Result<(FileAttr, u64), libc::c_int> {
+    let stat = tree.stat(ino)?;
+    tree.remember(ino);
+    Ok((file_attr(stat, owner), stat.generation))
+}
 
-    fn get_entry_by_name(&self, name: &str) -> Option<(u64, &str)> {
-        self.entries
-            .iter()
-            .find(|(_, entry_name)| entry_name == name)
-            .map(|(inode, name)| (*inode, name.as_str()))
+/// Replies to an extended attribute request with `data`, or with its size when the caller
+/// asked for it by passing a size of zero.
+fn reply_xattr(data: &[u8], size: u32, reply: ReplyXattr) {
+    if size == 0 {
+        reply.size(data.len() as u32);
+    } else if data.len() > size as usize {
+        reply.error(libc::ERANGE);
+    } else {
+        reply.data(data);
     }
 }
 
 impl Filesystem for QuiverFS {
+    fn destroy(&mut self) {
+        // Files still open when the filesystem is unmounted are never flushed by the kernel
+        self.lock().flush_all();
+    }
+
     fn lookup(&mut self, _req: &Request, parent: u64, name: &OsStr, reply: ReplyEntry) {
-        if parent != 1 {
+        let mut tree = self.lock();
+        let Some(name) = name.to_str() else {
             reply.error(libc::ENOENT);
             return;
-        }
-
-        if let Some(name_str) = name.to_str() {
-            if let Some((inode, _)) = self.get_entry_by_name(name_str) {
-                let attr = FileAttr {
-                    ino: inode,
-                    size: 0,
-                    blocks: 0,
-                    atime: UNIX_EPOCH,
-                    mtime: UNIX_EPOCH,
-                    ctime: UNIX_EPOCH,
-                    crtime: UNIX_EPOCH,
-                    kind: FileType::Directory,
-                    perm: 0o755,
-                    nlink: 2,
-                    uid: 501,
-                    gid: 20,
-                    rdev: 0,
-                    flags: 0,
-                    blksize: 512,
-                };
-                reply.entry(&TTL, &attr, 0);
+        };
+        let ino = match tree.lookup(parent, name) {
+            Ok(ino) => ino,
+            Err(errno) => {
+                reply.error(errno);
                 return;
             }
+        };
+        match entry(&mut tree, ino, self.owner) {
+            Ok((attr, generation)) => reply.entry(&self.ttl, &attr, generation),
+            Err(errno) => reply.error(errno),
         }
-        reply.error(libc::ENOENT);
+    }
+
+    fn forget(&mut self, _req: &Request, ino: u64, nlookup: u64) {
+        self.lock().forget(ino, nlookup);
     }
 
     fn getattr(&mut self, _req: &Request, ino: u64, _fh: Option<u64>, reply: ReplyAttr) {
-        let attr = if ino == 1 {
-            // Root directory
-            FileAttr {
-                ino: 1,
-                size: 0,
-                blocks: 0,
-                atime: UNIX_EPOCH,
-                mtime: UNIX_EPOCH,
-                ctime: UNIX_EPOCH,
-                crtime: UNIX_EPOCH,
-                kind: FileType::Directory,
-                perm: 0o755,
-                nlink: 2,
-                uid: 501,
-                gid: 20,
-                rdev: 0,
-                flags: 0,
-                blksize: 512,
-            }
-        } else if self.entries.iter().any(|(entry_ino, _)| *entry_ino == ino) {
-            // Table directory
-            FileAttr {
-                ino,
-                size: 0,
-                blocks: 0,
-                atime: UNIX_EPOCH,
-                mtime: UNIX_EPOCH,
-                ctime: UNIX_EPOCH,
-                crtime: UNIX_EPOCH,
-                kind: FileType::Directory,
-                perm: 0o755,
-                nlink: 2,
-                uid: 501,
-                gid: 20,
-                rdev: 0,
-                flags: 0,
-                blksize: 512,
+        match attr(&mut self.lock(), ino, self.owner) {
+            Ok(attr) => reply.attr(&self.ttl, &attr),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn setattr(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        mode: Option<u32>,
+        uid: Option<u32>,
+        gid: Option<u32>,
+        size: Option<u64>,
+        _atime: Option<TimeOrNow>,
+        _mtime: Option<TimeOrNow>,
+        _ctime: Option<SystemTime>,
+        _fh: Option<u64>,
+        _crtime: Option<SystemTime>,
+        _chgtime: Option<SystemTime>,
+        _bkuptime: Option<SystemTime>,
+        _flags: Option<u32>,
+        reply: ReplyAttr,
+    ) {
+        let mut tree = self.lock();
+        // Only the size of uploads can change; timestamp updates are accepted and ignored, and
+        // mode or ownership changes fail with `EPERM`, which `cp -p` and rsync only warn about
+        let result = match size {
+            Some(size) => tree.truncate(ino, size),
+            None if mode.is_some() || uid.is_some() || gid.is_some() => Err(libc::EPERM),
+            None => Ok(()),
+        };
+        match result.and_then(|_| attr(&mut tree, ino, self.owner)) {
+            Ok(attr) => reply.attr(&self.ttl, &attr),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn create(
+        &mut self,
+        _req: &Request,
+        parent: u64,
+        name: &OsStr,
+        _mode: u32,
+        _umask: u32,
+        _flags: i32,
+        reply: ReplyCreate,
+    ) {
+        let mut tree = self.lock();
+        let result = name
+            .to_str()
+            .ok_or(libc::EINVAL)
+            .and_then(|name| tree.create_upload(parent, name))
+            .and_then(|ino| entry(&mut tree, ino, self.owner));
+        match result {
+            Ok((attr, generation)) => reply.created(&self.ttl, &attr, generation, 0, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn write(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        data: &[u8],
+        _write_flags: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        match self.lock().write(ino, offset.max(0) as u64, data) {
+            Ok(written) => reply.written(written as u32),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn flush(&mut self, _req: &Request, ino: u64, _fh: u64, _lock_owner: u64, reply: ReplyEmpty) {
+        // Writes are committed when the file is closed, so that errors are reported by close()
+        match self.lock().flush(ino) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn release(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        _fh: u64,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        _flush: bool,
+        reply: ReplyEmpty,
+    ) {
+        self.lock().release(ino);
+        reply.ok();
+    }
+
+    fn open(&mut self, _req: &Request, ino: u64, flags: i32, reply: ReplyOpen) {
+        let write = flags & libc::O_ACCMODE != libc::O_RDONLY;
+        match self.lock().open(ino, write) {
+            Ok(()) => reply.opened(0, 0),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn read(
+        &mut self,
+        _req: &Request,
+        ino: u64,
+        _fh: u64,
+        offset: i64,
+        size: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyData,
+    ) {
+        match self.lock().read(ino, offset.max(0) as u64, size as usize) {
+            Ok(data) => reply.data(data),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    fn getxattr(&mut self, _req: &Request, ino: u64, name: &OsStr, size: u32, reply: ReplyXattr) {
+        let attributes = match self.lock().attributes(ino) {
+            Ok(attributes) => attributes,
+            Err(errno) => {
+                reply.error(errno);
+                return;
             }
-        } else {
-            reply.error(libc::ENOENT);
-            return;
         };
-        reply.attr(&TTL, &attr);
+        match name.to_str().and_then(|name| attributes.get(name)) {
+            Some(value) => reply_xattr(value.as_bytes(), size, reply),
+            None => reply.error(libc::ENODATA),
+        }
+    }
+
+    fn listxattr(&mut self, _req: &Request, ino: u64, size: u32, reply: ReplyXattr) {
+        match self.lock().attributes(ino) {
+            Ok(attributes) => {
+                // Names are listed one after the other, each ending with a null byte
+                let mut names = Vec::new();
+                for name in attributes.keys() {
+                    names.extend_from_slice(name.as_bytes());
+                    names.push(0);
+                }
+                reply_xattr(&names, size, reply);
+            }
+            Err(errno) => reply.error(errno),
+        }
     }
 
     fn readdir(
@@ -136,21 +362,23 @@ impl Filesystem for QuiverFS {
         offset: i64,
         mut reply: ReplyDirectory,
     ) {
-        if ino != 1 {
-            reply.error(libc::ENOENT);
-            return;
-        }
+        let tree = self.lock();
+        let (parent, children) = match (tree.parent(ino), tree.list(ino)) {
+            (Ok(parent), Ok(children)) => (parent, children),
+            (Err(errno), _) | (_, Err(errno)) => {
+                reply.error(errno);
+                return;
+            }
+        };
 
         let mut entries = vec![
-            (1, FileType::Directory, "."),
-            (1, FileType::Directory, ".."),
+            (ino, FileType::Directory, "."),
+            (parent, FileType::Directory, ".."),
         ];
-
-        // Add all discovered tables as directories
         entries.extend(
-            self.entries
+            children
                 .iter()
-                .map(|(inode, name)| (*inode, FileType::Directory, name.as_str())),
+                .map(|child| (child.ino, file_type(child.kind), child.name.as_str())),
         );
 
         for (i, entry) in entries.into_iter().enumerate().skip(offset as usize) {
@@ -161,3 +389,41 @@ impl Filesystem for QuiverFS {
         reply.ok();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use quiverfs_vfs::ROOT_INO;
+    use std::os::unix::fs::MetadataExt;
+
+    #[test]
+    fn test_file_attr() {
+        let dir = tempfile::tempdir().unwrap();
+        let meta = dir.path().metadata().unwrap();
+        let mut tree = Tree::new(dir.path().to_path_buf());
+
+        // The root reports the owner and timestamps of the source directory
+        let root = attr(&mut tree, ROOT_INO, OwnerOverride::default()).unwrap();
+        assert_eq!(root.kind, FileType::Directory);
+        assert_eq!((root.uid, root.gid), (meta.uid(), meta.gid()));
+        assert_eq!(root.mtime, meta.modified().unwrap());
+
+        let owner = OwnerOverride {
+            uid: Some(1000),
+            gid: None,
+        };
+        let root = attr(&mut tree, ROOT_INO, owner).unwrap();
+        assert_eq!((root.uid, root.gid), (1000, meta.gid()));
+
+        let stat = Stat {
+            kind: EntryKind::File,
+            size: 1000,
+            ..tree.stat(ROOT_INO).unwrap()
+        };
+        let file = file_attr(stat, owner);
+        assert_eq!(
+            (file.kind, file.size, file.blocks),
+            (FileType::RegularFile, 1000, 2)
+        );
+    }
+}
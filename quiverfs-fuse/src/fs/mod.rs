@@ -1,2 +1,5 @@
 mod filesystem;
-pub use filesystem::QuiverFS;
+mod watcher;
+pub use filesystem::{OwnerOverride, QuiverFS};
+pub use quiverfs_vfs::{CollisionPolicy, Config, ConflictPolicy, Layout, Source};
+pub use watcher::watch;
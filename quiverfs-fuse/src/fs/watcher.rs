@@ -0,0 +1,93 @@
+use fuser::Notifier;
+use notify::event::{AccessKind, AccessMode, Event, EventKind};
+use notify::{RecommendedWatcher, RecursiveMode, Watcher};
+use quiverfs_vfs::{Invalidation, Tree};
+use std::ffi::OsStr;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Quiet period to wait for after a change before rescanning, so that a burst of writes
+/// only triggers one refresh.
+const DEBOUNCE: Duration = Duration::from_millis(500);
+/// Longest time a refresh is postponed while changes keep arriving.
+const MAX_DELAY: Duration = Duration::from_secs(5);
+
+/// Checks if an event may change the tables in the source directories. Reads, including the
+/// ones made while rendering virtual files, are ignored.
+fn is_change(event: &Event) -> bool {
+    matches!(
+        event.kind,
+        EventKind::Create(_)
+            | EventKind::Modify(_)
+            | EventKind::Remove(_)
+            | EventKind::Access(AccessKind::Close(AccessMode::Write))
+    )
+}
+
+/// Blocks until a change arrives and then until changes stop for `DEBOUNCE`. Returns `false`
+/// once the watcher has been dropped.
+fn wait_for_changes(rx: &Receiver<notify::Result<Event>>) -> bool {
+    loop {
+        match rx.recv() {
+            Ok(Ok(event)) if is_change(&event) => break,
+            Ok(_) => continue,
+            Err(_) => return false,
+        }
+    }
+    let deadline = Instant::now() + MAX_DELAY;
+    while Instant::now() < deadline {
+        match rx.recv_timeout(DEBOUNCE) {
+            Ok(_) => continue,
+            Err(mpsc::RecvTimeoutError::Timeout) => break,
+            Err(mpsc::RecvTimeoutError::Disconnected) => return false,
+        }
+    }
+    true
+}
+
+/// Watches the source directories of `tree` and rescans them whenever tables are added, removed
+/// or rewritten, invalidating the kernel's cached entries and attributes through `notifier`.
+///
+/// Watching stops when the returned watcher is dropped.
+pub fn watch(tree: Arc<Mutex<Tree>>, notifier: Notifier) -> notify::Result<RecommendedWatcher> {
+    let sources = tree
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner)
+        .sources()
+        .to_vec();
+    let (tx, rx) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx)?;
+    for source in &sources {
+        watcher.watch(&source.path, RecursiveMode::Recursive)?;
+    }
+
+    thread::spawn(move || {
+        while wait_for_changes(&rx) {
+            // Scan without holding the tree, which would block every request to the mount until
+            // the scan is done. Changes made through the mount meanwhile rescan on their own and
+            // also raise events that trigger another scan.
+            let scanner = tree
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .scanner();
+            let scan = scanner.scan();
+            let stale = tree
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .update(scan);
+            // Notify outside the lock, since the kernel may call back into the filesystem.
+            // Entries the kernel never cached are reported as errors, which are harmless.
+            for invalidation in stale {
+                let _ = match invalidation {
+                    Invalidation::Entry { parent, name } => {
+                        notifier.inval_entry(parent, OsStr::new(&name))
+                    }
+                    Invalidation::Inode(ino) => notifier.inval_inode(ino, 0, 0),
+                };
+            }
+        }
+    });
+    Ok(watcher)
+}
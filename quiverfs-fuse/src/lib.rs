@@ -0,0 +1,10 @@
+mod args;
+mod daemon;
+mod fs;
+mod mount;
+
+pub use args::MountArgs;
+pub use fs::{
+    CollisionPolicy, Config, ConflictPolicy, Layout, OwnerOverride, QuiverFS, Source, watch,
+};
+pub use mount::{mount, unmount};
@@ -1,15 +1,18 @@
-mod fs;
-use fs::QuiverFS;
-use fuser::MountOption;
-use std::path::PathBuf;
+use clap::Parser;
+use quiverfs_fuse::{MountArgs, mount};
 
-fn main() {
-    let source_dir = std::env::args().nth(1).unwrap_or_else(|| ".".to_string());
-    let mount_point = std::env::args()
-        .nth(2)
-        .unwrap_or_else(|| "/tmp/quiver-fs".to_string());
-
-    let filesystem = QuiverFS::new(PathBuf::from(source_dir));
+#[derive(Parser)]
+#[command(name = "quiverfs-fuse")]
+#[command(about = "Mount a directory of Arrow and Parquet files as a filesystem", long_about = None)]
+struct Cli {
+    #[command(flatten)]
+    mount: MountArgs,
+}
 
-    fuser::mount2(filesystem, &mount_point, &[MountOption::RO]).unwrap();
+fn main() {
+    let cli = Cli::parse();
+    if let Err(e) = mount(&cli.mount) {
+        eprintln!("Error: {e}");
+        std::process::exit(1);
+    }
 }
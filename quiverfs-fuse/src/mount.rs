@@ -0,0 +1,312 @@
+use crate::args::{MountArgs, MountConfig};
+use crate::daemon::{self, PidFile, Signals};
+use crate::fs::{self, Config, QuiverFS, Source};
+use fuser::{BackgroundSession, Session};
+use notify::RecommendedWatcher;
+use std::io::{self, Write};
+use std::os::unix::fs::MetadataExt;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::Duration;
+
+/// How often the main thread checks whether the filesystem was unmounted by someone else
+/// while it waits for a signal.
+const SIGNAL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Checks that the mountpoint is a directory that nothing is mounted on, returning its
+/// absolute path.
+fn check_mountpoint(path: &Path) -> Result<PathBuf, String> {
+    let meta = path.metadata().map_err(|e| mountpoint_error(path, e))?;
+    if !meta.is_dir() {
+        return Err(format!("Mountpoint {} is not a directory", path.display()));
+    }
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("Cannot access mountpoint {}: {e}", path.display()))?;
+    // A directory on another device than its parent already has a filesystem mounted on it
+    if let Some(parent) = path.parent()
+        && parent
+            .metadata()
+            .is_ok_and(|parent| parent.dev() != meta.dev())
+    {
+        return Err(format!(
+            "{} is busy, another filesystem is already mounted on it",
+            path.display()
+        ));
+    }
+    Ok(path)
+}
+
+/// Describes why the mountpoint cannot be accessed.
+fn mountpoint_error(path: &Path, e: io::Error) -> String {
+    if e.raw_os_error() == Some(libc::ENOTCONN) {
+        format!(
+            "{} is left over from a filesystem that is no longer running \
+             (transport endpoint is not connected), unmount it with `fusermount -u {}`",
+            path.display(),
+            path.display()
+        )
+    } else if e.kind() == io::ErrorKind::NotFound {
+        format!("Mountpoint {} does not exist", path.display())
+    } else {
+        format!("Cannot access mountpoint {}: {e}", path.display())
+    }
+}
+
+/// Returns the absolute paths of the source directories, checking that they exist and that
+/// none of them contains another.
+fn check_sources(sources: &[Source]) -> Result<Vec<Source>, String> {
+    let mut checked: Vec<Source> = Vec::new();
+    for source in sources {
+        let path = source.path.canonicalize().map_err(|e| {
+            format!(
+                "Cannot open source directory {}: {e}",
+                source.path.display()
+            )
+        })?;
+        if !path.is_dir() {
+            return Err(format!("{} is not a directory", source.path.display()));
+        }
+        if let Some(other) = checked
+            .iter()
+            .find(|other| path.starts_with(&other.path) || other.path.starts_with(&path))
+        {
+            return Err(format!(
+                "Sources '{}' and '{}' overlap, as one of them contains the other",
+                other.name, source.name
+            ));
+        }
+        checked.push(Source {
+            name: source.name.clone(),
+            path,
+        });
+    }
+    Ok(checked)
+}
+
+/// Scans the source directories, mounts them, starts following their changes and serves
+/// them from a background thread.
+fn start(
+    sources: Vec<Source>,
+    tables: Config,
+    mountpoint: &Path,
+    config: &MountConfig,
+) -> Result<(BackgroundSession, RecommendedWatcher), String> {
+    let mut filesystem = QuiverFS::with_sources(sources, config.layout)
+        .with_config(tables)
+        .with_owner(config.owner)
+        .with_read_write(config.read_write)
+        .with_collision_policy(config.collisions)
+        .map_err(|e| format!("Table names collide:\n{e}"))?
+        .check_conflicts()
+        .map_err(|e| format!("Tables of the sources conflict:\n{e}"))?;
+    if let Some(ttl) = config.ttl {
+        filesystem = filesystem.with_ttl(ttl);
+    }
+    let tree = filesystem.tree();
+
+    let session =
+        Session::new(filesystem, mountpoint, &config.mount_options()).map_err(|e| {
+            match e.raw_os_error() {
+                Some(libc::EBUSY) => format!("{} is busy", mountpoint.display()),
+                _ => format!("Cannot mount on {}: {e}", mountpoint.display()),
+            }
+        })?;
+    let watcher = fs::watch(tree, session.notifier())
+        .map_err(|e| format!("Cannot watch the source directories for changes: {e}"))?;
+    let session = session
+        .spawn()
+        .map_err(|e| format!("Cannot start serving the filesystem: {e}"))?;
+    Ok((session, watcher))
+}
+
+/// Unmounts a running session and waits for its thread to finish, which commits the
+/// writes still pending.
+fn stop(session: BackgroundSession) -> Result<(), String> {
+    // `join` panics when serving the filesystem failed, with the error as the message
+    panic::catch_unwind(AssertUnwindSafe(|| session.join())).map_err(|payload| {
+        let message = payload
+            .downcast_ref::<String>()
+            .map(String::as_str)
+            .or_else(|| payload.downcast_ref::<&str>().copied());
+        match message {
+            Some(message) => format!("The filesystem stopped: {message}"),
+            None => "The filesystem stopped unexpectedly".to_string(),
+        }
+    })
+}
+
+/// Mounts one or more directories of Arrow and Parquet files and serves it until it is unmounted or
+/// receives SIGINT, SIGTERM or SIGHUP, which unmount it after committing pending writes.
+///
+/// When running as a daemon, the calling process exits once the filesystem is mounted. The
+/// service manager is told when the filesystem is ready and when it stops if `NOTIFY_SOCKET`
+/// is set, as systemd does for `Type=notify` services.
+///
+/// Problems found while serving the filesystem are logged with the `log` crate. Unless the
+/// program installed a logger of its own, they are printed on standard error, showing
+/// warnings and errors or the levels selected by `RUST_LOG`.
+pub fn mount(args: &MountArgs) -> Result<(), String> {
+    init_logging();
+    let config = args.config()?;
+    let sources = check_sources(&config.sources)?;
+    let mountpoint = check_mountpoint(&config.mountpoint)?;
+    let tables = match &config.config {
+        Some(path) => Config::load(path)?,
+        None => Config::default(),
+    };
+    // The daemon changes its working directory
+    let pidfile = match &config.pidfile {
+        Some(path) => Some(
+            std::path::absolute(path)
+                .map_err(|e| format!("Cannot write pid file {}: {e}", path.display()))?,
+        ),
+        None => None,
+    };
+
+    // The daemon forks, and the signals are blocked, before any other thread starts
+    let readiness = if config.daemon {
+        Some(daemon::daemonize()?)
+    } else {
+        None
+    };
+    let started = Signals::block().and_then(|signals| {
+        let (session, watcher) = start(sources, tables, &mountpoint, &config)?;
+        let pidfile = pidfile.as_deref().map(PidFile::create).transpose()?;
+        Ok((signals, session, watcher, pidfile))
+    });
+    // Keep the watcher and the pid file for as long as the filesystem is mounted
+    let (signals, session, _watcher, _pidfile) = match (started, readiness) {
+        (Ok(started), Some(readiness)) => {
+            readiness.ready();
+            started
+        }
+        (Ok(started), None) => started,
+        (Err(e), Some(readiness)) => {
+            readiness.fail(&e);
+            std::process::exit(1);
+        }
+        (Err(e), None) => return Err(e),
+    };
+    daemon::notify("READY=1");
+
+    while !session.guard.is_finished() {
+        if let Some(signal) = signals.wait(SIGNAL_POLL_INTERVAL) {
+            log::info!("Received {signal}, unmounting {}", mountpoint.display());
+            break;
+        }
+    }
+    daemon::notify("STOPPING=1");
+    stop(session)
+}
+
+/// Installs a logger printing messages as `Warning: ...` or `Error: ...` on standard error,
+/// unless a logger is already installed.
+fn init_logging() {
+    let env = env_logger::Env::default().default_filter_or("warn");
+    let _ = env_logger::Builder::from_env(env)
+        .format(|buf, record| {
+            let level = match record.level() {
+                log::Level::Error => "Error",
+                log::Level::Warn => "Warning",
+                level => level.as_str(),
+            };
+            writeln!(buf, "{level}: {}", record.args())
+        })
+        .try_init();
+}
+
+/// Unmounts a filesystem mounted by [`mount`] with the `fusermount` helper, which lets the
+/// user who mounted it unmount it without being root.
+pub fn unmount(mountpoint: &Path) -> Result<(), String> {
+    for helper in ["fusermount3", "fusermount"] {
+        let output = match Command::new(helper).arg("-u").arg(mountpoint).output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(format!("Cannot run {helper}: {e}")),
+        };
+        if output.status.success() {
+            return Ok(());
+        }
+        let message = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "Cannot unmount {}: {}",
+            mountpoint.display(),
+            message.trim()
+        ));
+    }
+    Err("Cannot unmount: neither fusermount3 nor fusermount is installed".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_check_mountpoint() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        std::fs::create_dir(root.join("mnt")).unwrap();
+        std::fs::write(root.join("file"), b"").unwrap();
+
+        assert_eq!(
+            check_mountpoint(&root.join("mnt/../mnt")),
+            Ok(root.join("mnt"))
+        );
+        assert_eq!(
+            check_mountpoint(&root.join("missing")),
+            Err(format!(
+                "Mountpoint {} does not exist",
+                root.join("missing").display()
+            ))
+        );
+        assert!(
+            check_mountpoint(&root.join("file"))
+                .unwrap_err()
+                .ends_with("is not a directory")
+        );
+
+        let stale = mountpoint_error(
+            Path::new("/mnt/q"),
+            io::Error::from_raw_os_error(libc::ENOTCONN),
+        );
+        assert!(stale.starts_with("/mnt/q is left over"), "{stale}");
+        assert!(stale.ends_with("`fusermount -u /mnt/q`"), "{stale}");
+    }
+
+    #[test]
+    fn test_check_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().canonicalize().unwrap();
+        for name in ["raw", "raw/2024", "curated"] {
+            std::fs::create_dir(root.join(name)).unwrap();
+        }
+        std::fs::write(root.join("file"), b"").unwrap();
+        let source = |name: &str, path: &str| Source {
+            name: name.to_string(),
+            path: root.join(path),
+        };
+
+        let checked = check_sources(&[source("raw", "raw/."), source("curated", "curated")]);
+        let paths: Vec<_> = checked.unwrap().into_iter().map(|s| s.path).collect();
+        assert_eq!(paths, [root.join("raw"), root.join("curated")]);
+
+        assert_eq!(
+            check_sources(&[source("raw", "raw"), source("year", "raw/2024")]),
+            Err("Sources 'raw' and 'year' overlap, as one of them contains the other".to_string())
+        );
+        assert!(check_sources(&[source("year", "raw/2024"), source("raw", "raw")]).is_err());
+        assert!(check_sources(&[source("a", "raw"), source("b", "curated/../raw")]).is_err());
+        assert!(
+            check_sources(&[source("missing", "missing")])
+                .unwrap_err()
+                .starts_with("Cannot open source directory")
+        );
+        assert!(
+            check_sources(&[source("file", "file")])
+                .unwrap_err()
+                .ends_with("is not a directory")
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use std::collections::{BTreeMap, HashMap};
+
+/// How many bytes of rendered virtual files are kept by default.
+pub const DEFAULT_CACHE_SIZE: usize = 256 << 20;
+
+/// The rendered contents of virtual files, by inode, holding at most `capacity` bytes. The
+/// least recently used files are dropped to make room, and are rendered again when read.
+/// Files with open handles are never dropped, as they would be rendered again for every read,
+/// so the cache grows past its capacity while they do not fit.
+pub struct RenderCache {
+    files: HashMap<u64, (Vec<u8>, u64)>, // contents and when they were last used
+    by_use: BTreeMap<u64, u64>,          // inodes by when they were last used, oldest first
+    open: HashMap<u64, usize>,           // open handles, by inode
+    bytes: usize,
+    capacity: usize,
+    clock: u64,
+}
+
+impl RenderCache {
+    pub fn new(capacity: usize) -> Self {
+        RenderCache {
+            files: HashMap::new(),
+            by_use: BTreeMap::new(),
+            open: HashMap::new(),
+            bytes: 0,
+            capacity,
+            clock: 0,
+        }
+    }
+
+    /// Returns the contents of a file, marking them as used.
+    pub fn get(&mut self, ino: u64) -> Option<&[u8]> {
+        let (data, used) = self.files.get_mut(&ino)?;
+        self.by_use.remove(used);
+        self.clock += 1;
+        *used = self.clock;
+        self.by_use.insert(self.clock, ino);
+        Some(data)
+    }
+
+    /// Returns the size of a file if its contents are cached.
+    pub fn size(&self, ino: u64) -> Option<usize> {
+        self.files.get(&ino).map(|(data, _)| data.len())
+    }
+
+    /// Caches the contents of a file, dropping the least recently used other files without
+    /// open handles while the cache is over capacity. A file larger than the capacity is kept
+    /// until the next insert.
+    pub fn insert(&mut self, ino: u64, data: Vec<u8>) -> &[u8] {
+        self.remove(ino);
+        self.bytes += data.len();
+        let mut excess = self.bytes.saturating_sub(self.capacity);
+        let mut dropped = Vec::new();
+        for file in self.by_use.values() {
+            if excess == 0 {
+                break;
+            }
+            if !self.open.contains_key(file) {
+                excess = excess.saturating_sub(self.files[file].0.len());
+                dropped.push(*file);
+            }
+        }
+        for file in dropped {
+            self.remove(file);
+        }
+        self.clock += 1;
+        self.by_use.insert(self.clock, ino);
+        let (data, _) = self.files.entry(ino).or_insert((data, self.clock));
+        data
+    }
+
+    pub fn remove(&mut self, ino: u64) {
+        if let Some((data, used)) = self.files.remove(&ino) {
+            self.by_use.remove(&used);
+            self.bytes -= data.len();
+        }
+    }
+
+    /// Records that a handle to a file was opened, keeping its contents until it is closed.
+    pub fn open(&mut self, ino: u64) {
+        *self.open.entry(ino).or_default() += 1;
+    }
+
+    /// Records that a handle to a file was closed.
+    pub fn close(&mut self, ino: u64) {
+        if let Some(count) = self.open.get_mut(&ino) {
+            *count -= 1;
+            if *count == 0 {
+                self.open.remove(&ino);
+            }
+        }
+    }
+
+    /// Keeps only the files for which `keep` returns true.
+    pub fn retain<F: FnMut(u64) -> bool>(&mut self, mut keep: F) {
+        self.files.retain(|ino, _| keep(*ino));
+        self.by_use = self
+            .files
+            .iter()
+            .map(|(ino, (_, used))| (*used, *ino))
+            .collect();
+        self.bytes = self.files.values().map(|(data, _)| data.len()).sum();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_least_recently_used_files_are_dropped() {
+        let mut cache = RenderCache::new(10);
+        cache.insert(1, vec![0; 4]);
+        cache.insert(2, vec![0; 4]);
+        assert!(cache.get(1).is_some());
+        cache.insert(3, vec![0; 4]);
+        assert_eq!(
+            (cache.size(1), cache.size(2), cache.size(3)),
+            (Some(4), None, Some(4))
+        );
+
+        // A file larger than the cache replaces everything else
+        cache.insert(4, vec![0; 20]);
+        assert_eq!(
+            (cache.size(1), cache.size(3), cache.size(4)),
+            (None, None, Some(20))
+        );
+        cache.insert(5, vec![0; 1]);
+        assert_eq!((cache.size(4), cache.size(5)), (None, Some(1)));
+
+        cache.retain(|ino| ino != 5);
+        assert_eq!(cache.size(5), None);
+        assert_eq!((cache.bytes, cache.by_use.len()), (0, 0));
+    }
+
+    #[test]
+    fn test_open_files_are_kept() {
+        let mut cache = RenderCache::new(10);
+        cache.insert(1, vec![0; 8]);
+        cache.open(1);
+        cache.open(1);
+        cache.insert(2, vec![0; 8]);
+        cache.insert(3, vec![0; 4]);
+        assert_eq!(
+            (cache.size(1), cache.size(2), cache.size(3)),
+            (Some(8), None, Some(4))
+        );
+
+        // Files are dropped once their last handle is closed
+        cache.close(1);
+        cache.insert(4, vec![0; 4]);
+        assert_eq!(cache.size(1), Some(8));
+        cache.close(1);
+        cache.insert(5, vec![0; 4]);
+        assert_eq!(
+            (cache.size(1), cache.size(3), cache.size(4), cache.size(5)),
+            (None, None, Some(4), Some(4))
+        );
+    }
+}
@@ -0,0 +1,33 @@
+use std::time::SystemTime;
+
+/// Whether an entry of the tree is a directory or a regular file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryKind {
+    Directory,
+    File,
+}
+
+/// One entry of a directory listing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DirEntry {
+    pub ino: u64,
+    pub kind: EntryKind,
+    pub name: String,
+}
+
+/// The attributes of an entry, with the owner and timestamps of its source file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stat {
+    pub ino: u64,
+    /// Changes when the inode number is reused for another entry.
+    pub generation: u64,
+    pub kind: EntryKind,
+    pub size: u64,
+    pub perm: u16,
+    pub nlink: u32,
+    pub uid: u32,
+    pub gid: u32,
+    pub atime: SystemTime,
+    pub mtime: SystemTime,
+    pub ctime: SystemTime,
+}
@@ -0,0 +1,185 @@
+use crate::tree::ROOT_INO;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// What an inode stands for: a file or directory of the source tree, or a virtual entry
+/// below one, such as `where/country=DE/data.csv` below the table `sales.parquet`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InodeKey {
+    pub source: PathBuf,
+    pub sub_path: PathBuf,
+}
+
+impl InodeKey {
+    pub fn source(path: &Path) -> Self {
+        InodeKey {
+            source: path.to_path_buf(),
+            sub_path: PathBuf::new(),
+        }
+    }
+
+    /// The key of the virtual entry `name` below this one.
+    pub fn child(&self, name: &str) -> Self {
+        InodeKey {
+            source: self.source.clone(),
+            sub_path: self.sub_path.join(name),
+        }
+    }
+
+    /// Hashes the key with 64-bit FNV-1a, which unlike the standard library's hasher is the
+    /// same in every build, so that inode numbers survive remounts and upgrades.
+    fn hash(&self) -> u64 {
+        let source = self.source.as_os_str().as_encoded_bytes();
+        let sub_path = self.sub_path.as_os_str().as_encoded_bytes();
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in source.iter().chain(&[0]).chain(sub_path) {
+            hash ^= u64::from(*byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+        hash
+    }
+}
+
+struct Inode {
+    key: InodeKey,
+    generation: u64,
+    /// References handed to the kernel and not forgotten yet.
+    lookups: u64,
+}
+
+/// Inode numbers of the entries of a tree, derived from their [`InodeKey`].
+///
+/// Keys that hash to a number already in use take the next free one. A number that is freed
+/// and then given to another key gets a new generation, so that clients holding on to the old
+/// entry, such as NFS servers, can tell the two apart.
+#[derive(Default)]
+pub struct InodeTable {
+    inodes: HashMap<u64, Inode>,
+    by_key: HashMap<InodeKey, u64>,
+    /// The key and generation each freed number had last, used when the number is reused.
+    freed: HashMap<u64, (InodeKey, u64)>,
+}
+
+impl InodeTable {
+    /// Returns the inode number of `key`, assigning one if it has none.
+    pub fn assign(&mut self, key: InodeKey) -> u64 {
+        let start = key.hash();
+        self.assign_from(key, start)
+    }
+
+    fn assign_from(&mut self, key: InodeKey, start: u64) -> u64 {
+        if let Some(ino) = self.by_key.get(&key) {
+            return *ino;
+        }
+        let mut ino = start;
+        // 0 is not a valid inode number and 1 is the root's
+        while ino <= ROOT_INO || self.inodes.contains_key(&ino) {
+            ino = ino.wrapping_add(1);
+        }
+        let generation = match self.freed.remove(&ino) {
+            Some((freed_key, generation)) if freed_key == key => generation,
+            Some((_, generation)) => generation + 1,
+            None => 0,
+        };
+        self.by_key.insert(key.clone(), ino);
+        self.inodes.insert(
+            ino,
+            Inode {
+                key,
+                generation,
+                lookups: 0,
+            },
+        );
+        ino
+    }
+
+    pub fn key(&self, ino: u64) -> Option<&InodeKey> {
+        self.inodes.get(&ino).map(|inode| &inode.key)
+    }
+
+    pub fn generation(&self, ino: u64) -> u64 {
+        self.inodes.get(&ino).map_or(0, |inode| inode.generation)
+    }
+
+    /// Records that a reference to `ino` was handed to the kernel.
+    pub fn remember(&mut self, ino: u64) {
+        if let Some(inode) = self.inodes.get_mut(&ino) {
+            inode.lookups += 1;
+        }
+    }
+
+    /// Drops `count` of the kernel's references to `ino`, freeing its number if it is no
+    /// longer `in_use` and nothing refers to it anymore.
+    pub fn forget(&mut self, ino: u64, count: u64, in_use: bool) {
+        if let Some(inode) = self.inodes.get_mut(&ino) {
+            inode.lookups = inode.lookups.saturating_sub(count);
+        }
+        if !in_use {
+            self.free_unreferenced(|candidate| candidate != ino);
+        }
+    }
+
+    /// Frees the numbers of the inodes that are not `in_use` and that the kernel has no
+    /// reference to.
+    pub fn free_unreferenced(&mut self, in_use: impl Fn(u64) -> bool) {
+        let unused: Vec<u64> = self
+            .inodes
+            .iter()
+            .filter(|(ino, inode)| inode.lookups == 0 && !in_use(**ino))
+            .map(|(ino, _)| *ino)
+            .collect();
+        for ino in unused {
+            if let Some(inode) = self.inodes.remove(&ino) {
+                self.by_key.remove(&inode.key);
+                self.freed.insert(ino, (inode.key, inode.generation));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(source: &str, sub_path: &str) -> InodeKey {
+        InodeKey::source(Path::new(source)).child(sub_path)
+    }
+
+    #[test]
+    fn test_numbers_are_derived_from_keys() {
+        let mut first = InodeTable::default();
+        let mut second = InodeTable::default();
+        let a = first.assign(key("/data/sales.parquet", "data.csv"));
+        let b = first.assign(key("/data/users.parquet", "data.csv"));
+        // Assigned in another order, as after a remount with new files
+        assert_eq!(second.assign(key("/data/users.parquet", "data.csv")), b);
+        assert_eq!(second.assign(key("/data/sales.parquet", "data.csv")), a);
+        assert_ne!(a, b);
+        assert_eq!(first.assign(key("/data/sales.parquet", "data.csv")), a);
+        assert_eq!(first.key(a), Some(&key("/data/sales.parquet", "data.csv")));
+    }
+
+    #[test]
+    fn test_colliding_keys_probe_and_reuse_bumps_generation() {
+        let mut table = InodeTable::default();
+        let a = table.assign_from(key("/a", ""), 10);
+        let b = table.assign_from(key("/b", ""), 10);
+        assert_eq!((a, b), (10, 11));
+        assert_eq!(table.assign_from(key("/c", ""), 0), 2);
+
+        // A held inode keeps its number after it leaves the tree, until it is forgotten
+        table.remember(a);
+        table.free_unreferenced(|ino| ino == b);
+        assert_eq!(table.key(a), Some(&key("/a", "")));
+        assert_eq!(table.key(2), None);
+        table.forget(a, 1, false);
+        assert_eq!(table.key(a), None);
+
+        // The same key taking its number back keeps its generation, another key gets a new one
+        let a = table.assign_from(key("/a", ""), 10);
+        assert_eq!((a, table.generation(a)), (10, 0));
+        table.free_unreferenced(|ino| ino == b);
+        let c = table.assign_from(key("/c", ""), 10);
+        assert_eq!((c, table.generation(c)), (10, 1));
+    }
+}
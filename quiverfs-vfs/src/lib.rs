@@ -0,0 +1,15 @@
+mod cache;
+mod entry;
+mod inode;
+mod naming;
+mod scan;
+mod source;
+mod tree;
+mod virtual_file;
+
+pub use entry::{DirEntry, EntryKind, Stat};
+pub use naming::CollisionPolicy;
+pub use quiverfs_core::Config;
+pub use scan::{Scan, Scanner};
+pub use source::{ConflictPolicy, Layout, Source};
+pub use tree::{Invalidation, ROOT_INO, Tree};
@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// How to name tables whose file stems collide with another entry of the same directory, such
+/// as `sales.arrow` next to `sales.parquet` or next to a `sales` directory.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CollisionPolicy {
+    /// Tables are named after their file stem, and files whose stem is shared get their
+    /// extension appended with a dash, as in `sales-arrow` and `sales-parquet`. Directories
+    /// keep their name.
+    #[default]
+    Suffix,
+    /// Tables stored in a single file are always named after the whole file name, as in
+    /// `sales.parquet`.
+    Extension,
+    /// Entries whose stem is shared are refused. Mounting fails, and entries that start to
+    /// collide while mounted are left out except for the first one.
+    Error,
+}
+
+impl FromStr for CollisionPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "suffix" => Ok(CollisionPolicy::Suffix),
+            "extension" => Ok(CollisionPolicy::Extension),
+            "error" => Ok(CollisionPolicy::Error),
+            _ => Err(format!(
+                "Unknown collision policy '{value}', expected suffix, extension or error"
+            )),
+        }
+    }
+}
+
+/// An entry of a source directory that needs a name in the mount.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub path: PathBuf,
+    /// The file stem of a data file, or the name of a directory.
+    pub stem: String,
+    /// The extension of a data file, or `None` for directories.
+    pub extension: Option<String>,
+}
+
+/// Names the entries of one source directory, in order.
+///
+/// Each entry gets its name, or the reason it is left out when its name is still taken
+/// after applying `policy`. Earlier entries win, so the result only depends on the order of
+/// the candidates.
+pub fn assign_names(
+    candidates: &[Candidate],
+    policy: CollisionPolicy,
+) -> Vec<Result<String, String>> {
+    let base = |candidate: &Candidate| match (&candidate.extension, policy) {
+        (Some(extension), CollisionPolicy::Extension) => {
+            format!("{}.{}", candidate.stem, extension)
+        }
+        _ => candidate.stem.clone(),
+    };
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for candidate in candidates {
+        *counts.entry(base(candidate)).or_default() += 1;
+    }
+
+    let mut taken: HashMap<String, &Candidate> = HashMap::new();
+    let mut names = Vec::new();
+    for candidate in candidates {
+        let mut name = base(candidate);
+        if policy == CollisionPolicy::Suffix
+            && counts[&name] > 1
+            && let Some(extension) = &candidate.extension
+        {
+            name = format!("{}-{}", candidate.stem, extension);
+        }
+        match taken.get(&name) {
+            Some(first) => names.push(Err(format!(
+                "{} is not shown because {} already appears as '{}'",
+                candidate.path.display(),
+                first.path.display(),
+                name
+            ))),
+            None => {
+                taken.insert(name.clone(), candidate);
+                names.push(Ok(name));
+            }
+        }
+    }
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(name: &str) -> Candidate {
+        let (stem, extension) = name.rsplit_once('.').unwrap();
+        Candidate {
+            path: PathBuf::from(name),
+            stem: stem.to_string(),
+            extension: Some(extension.to_string()),
+        }
+    }
+
+    fn dir(name: &str) -> Candidate {
+        Candidate {
+            path: PathBuf::from(name),
+            stem: name.to_string(),
+            extension: None,
+        }
+    }
+
+    fn names(candidates: &[Candidate], policy: CollisionPolicy) -> Vec<String> {
+        assign_names(candidates, policy)
+            .into_iter()
+            .map(|name| name.unwrap_or_else(|_| "<skipped>".to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_suffix_policy() {
+        let candidates = [
+            dir("sales"),
+            file("sales.arrow"),
+            file("sales.parquet"),
+            file("users.parquet"),
+        ];
+        assert_eq!(
+            names(&candidates, CollisionPolicy::Suffix),
+            ["sales", "sales-arrow", "sales-parquet", "users"]
+        );
+    }
+
+    #[test]
+    fn test_extension_policy() {
+        let candidates = [dir("sales"), file("sales.arrow"), file("users.parquet")];
+        assert_eq!(
+            names(&candidates, CollisionPolicy::Extension),
+            ["sales", "sales.arrow", "users.parquet"]
+        );
+    }
+
+    #[test]
+    fn test_error_policy_keeps_first() {
+        let candidates = [
+            file("sales.arrow"),
+            file("sales.parquet"),
+            file("users.arrow"),
+        ];
+        let names = assign_names(&candidates, CollisionPolicy::Error);
+        assert_eq!(names[0], Ok("sales".to_string()));
+        assert!(names[1].as_ref().unwrap_err().contains("sales.arrow"));
+        assert_eq!(names[2], Ok("users".to_string()));
+    }
+
+    #[test]
+    fn test_remaining_collisions_are_skipped() {
+        // `sales-arrow.parquet` has no collision of its own, but its name is taken by the
+        // suffixed `sales.arrow`
+        let candidates = [
+            file("sales.arrow"),
+            file("sales.parquet"),
+            file("sales-arrow.parquet"),
+        ];
+        assert_eq!(
+            names(&candidates, CollisionPolicy::Suffix),
+            ["sales-arrow", "sales-parquet", "<skipped>"]
+        );
+    }
+
+    #[test]
+    fn test_parse_policy() {
+        assert_eq!("extension".parse(), Ok(CollisionPolicy::Extension));
+        assert!("rename".parse::<CollisionPolicy>().is_err());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::naming::{Candidate, CollisionPolicy, assign_names};
+use crate::source::{ConflictPolicy, Layout, Source};
+use crate::tree::{SourceMeta, TableSource};
+use quiverfs_core::Dataset;
+use quiverfs_core::catalog::{CatalogEntry, list_entries};
+use quiverfs_core::config::{Config, DeclaredTables};
+use quiverfs_core::{has_complete_footer, is_feather_v1};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The settings a tree is scanned with, copied out of it so that the source directories can be
+/// read without holding the tree, which stays usable while a slow scan is in progress.
+#[derive(Clone)]
+pub struct Scanner {
+    pub(crate) sources: Vec<Source>,
+    pub(crate) layout: Layout,
+    pub(crate) collision_policy: CollisionPolicy,
+    pub(crate) config: Config,
+}
+
+/// The tables and directories found in the source directories, ready to be shown by a tree.
+pub struct Scan {
+    pub(crate) root: SourceMeta,
+    pub(crate) entries: Vec<ScanEntry>,
+    pub(crate) collisions: Vec<String>,
+    pub(crate) conflicts: Vec<String>,
+    pub(crate) warnings: Vec<String>,
+    declared_files: HashSet<PathBuf>, // files used by declared tables, which the scan skips
+}
+
+pub(crate) enum ScanEntry {
+    /// The top-level directory of a source when sources are mounted as namespaces. It is
+    /// shown even while it holds no tables.
+    Namespace {
+        source: usize,
+        name: String,
+        path: PathBuf,
+        meta: SourceMeta,
+        entries: Vec<ScanEntry>,
+    },
+    /// A directory with tables below it. Its path is the one of its first copy when it is
+    /// merged from several overlaid sources.
+    Directory {
+        name: String,
+        path: PathBuf,
+        meta: SourceMeta,
+        entries: Vec<ScanEntry>,
+    },
+    Table(Arc<TableSource>),
+}
+
+/// An entry of a source directory found by a scan, before it is named.
+enum Scanned {
+    /// A table declared in the configuration.
+    Declared(Dataset),
+    File(PathBuf),
+    /// A directory of part files.
+    Dataset(Vec<PathBuf>),
+    /// A directory, with each of its copies in overlaid sources and the index of their source.
+    Directory(Vec<(usize, PathBuf)>),
+}
+
+impl Scanner {
+    /// Reads the source directories and the schemas of the tables in them.
+    pub fn scan(&self) -> Scan {
+        let mut scan = Scan {
+            root: SourceMeta::of(&self.sources[0].path),
+            entries: Vec::new(),
+            collisions: Vec::new(),
+            conflicts: Vec::new(),
+            warnings: Vec::new(),
+            declared_files: HashSet::new(),
+        };
+        match self.layout {
+            Layout::Namespaces => {
+                for (index, source) in self.sources.iter().enumerate() {
+                    let dirs = [(index, source.path.clone())];
+                    let entries = self.scan_subdirectory(&mut scan, &dirs, true);
+                    scan.entries.push(ScanEntry::Namespace {
+                        source: index,
+                        name: source.name.clone(),
+                        path: source.path.clone(),
+                        meta: SourceMeta::of(&source.path),
+                        entries,
+                    });
+                }
+            }
+            Layout::Overlay(_) => {
+                let dirs: Vec<_> = self
+                    .sources
+                    .iter()
+                    .enumerate()
+                    .map(|(index, source)| (index, source.path.clone()))
+                    .collect();
+                scan.entries = self.scan_subdirectory(&mut scan, &dirs, true);
+            }
+        }
+        scan
+    }
+
+    /// Lists the tables the configuration declares in the source directories `dirs`, which
+    /// are overlaid when there are several, recording the files they use so that the scan
+    /// skips them.
+    fn declared_tables(
+        &self,
+        scan: &mut Scan,
+        dirs: &[(usize, PathBuf)],
+    ) -> (Vec<Candidate>, Vec<Scanned>) {
+        let dirs: Vec<&Path> = dirs.iter().map(|(_, dir)| dir.as_path()).collect();
+        let DeclaredTables { datasets, files } = self.config.declared_tables_across(&dirs);
+        scan.declared_files.extend(files);
+        let mut candidates = Vec::new();
+        let mut scanned = Vec::new();
+        for dataset in datasets {
+            if dataset.parts.is_empty() {
+                scan.warnings.extend(dataset.diagnostics);
+                continue;
+            }
+            candidates.push(Candidate {
+                path: dataset.root.clone(),
+                stem: dataset.name.clone(),
+                extension: None,
+            });
+            scanned.push(Scanned::Declared(dataset));
+        }
+        (candidates, scanned)
+    }
+
+    /// Lists the tables in source directories, recursing into subdirectories such as
+    /// Hive-style `year=2024/month=01` partitions. Directories of the same name in several
+    /// overlaid sources are merged, and directories without any tables below them are left
+    /// out.
+    ///
+    /// The tables declared in the configuration are added when `top` is set, as `dirs` are
+    /// then the source directories themselves. They are evaluated once for all of them and
+    /// named along with the entries of the first one.
+    fn scan_subdirectory(
+        &self,
+        scan: &mut Scan,
+        dirs: &[(usize, PathBuf)],
+        top: bool,
+    ) -> Vec<ScanEntry> {
+        let mut entries: Vec<(Candidate, Scanned)> = Vec::new();
+        let mut declared = if top {
+            self.declared_tables(scan, dirs)
+        } else {
+            (Vec::new(), Vec::new())
+        };
+        for (source, dir) in dirs {
+            let (mut candidates, mut scanned) = std::mem::take(&mut declared);
+            if !top || self.config.scan {
+                let (scan_candidates, scan_scanned) = scan_entries(scan, *source, dir);
+                candidates.extend(scan_candidates);
+                scanned.extend(scan_scanned);
+            }
+            // Entries are named together so that collisions are resolved the same way on every scan
+            let names = assign_names(&candidates, self.collision_policy);
+            for ((candidate, scanned), name) in candidates.into_iter().zip(scanned).zip(names) {
+                let name = match name {
+                    Ok(name) => name,
+                    Err(collision) => {
+                        scan.warnings.push(collision.clone());
+                        scan.collisions.push(collision);
+                        continue;
+                    }
+                };
+                if let Scanned::Directory(copies) = &scanned
+                    && let Some((_, Scanned::Directory(merged))) =
+                        entries.iter_mut().find(|(other, scanned)| {
+                            other.stem == name && matches!(scanned, Scanned::Directory(_))
+                        })
+                {
+                    merged.extend(copies.iter().cloned());
+                    continue;
+                }
+                // Tables of overlaid sources are told apart by the name of their source
+                let extension = (dirs.len() > 1 && !matches!(scanned, Scanned::Directory(_)))
+                    .then(|| self.sources[*source].name.clone());
+                let candidate = Candidate {
+                    path: candidate.path,
+                    stem: name,
+                    extension,
+                };
+                entries.push((candidate, scanned));
+            }
+        }
+
+        let policy = match self.layout {
+            Layout::Overlay(ConflictPolicy::Suffix) => CollisionPolicy::Suffix,
+            _ => CollisionPolicy::Error,
+        };
+        let candidates: Vec<Candidate> = entries.iter().map(|(c, _)| c.clone()).collect();
+        let names = assign_names(&candidates, policy);
+        let mut found = Vec::new();
+        for ((candidate, scanned), name) in entries.into_iter().zip(names) {
+            let name = match name {
+                Ok(name) => name,
+                Err(conflict) => {
+                    scan.warnings.push(conflict.clone());
+                    scan.conflicts.push(conflict);
+                    continue;
+                }
+            };
+            let dataset = match scanned {
+                Scanned::Declared(dataset) => Dataset { name, ..dataset },
+                Scanned::File(path) => Dataset::from_parts(name, path.clone(), vec![path]),
+                Scanned::Dataset(mut parts) => {
+                    parts.retain(|part| is_complete(part, &mut scan.warnings));
+                    Dataset::from_parts(name, candidate.path, parts)
+                }
+                Scanned::Directory(copies) => {
+                    let entries = self.scan_subdirectory(scan, &copies, false);
+                    if !entries.is_empty() {
+                        found.push(ScanEntry::Directory {
+                            name,
+                            meta: SourceMeta::of(&candidate.path),
+                            path: candidate.path,
+                            entries,
+                        });
+                    }
+                    continue;
+                }
+            };
+            found.push(ScanEntry::Table(Arc::new(TableSource::new(dataset))));
+        }
+        found
+    }
+}
+
+/// Checks whether a data file has been completely written. Feather v1 files never are, as
+/// they cannot be read, and are reported rather than skipped silently.
+fn is_complete(path: &Path, warnings: &mut Vec<String>) -> bool {
+    if has_complete_footer(path) {
+        return true;
+    }
+    if is_feather_v1(path) {
+        warnings.push(format!(
+            "{} is a Feather v1 file, which is not supported; convert it to Arrow IPC",
+            path.display()
+        ));
+    }
+    false
+}
+
+/// Lists the entries of the directory `dir` of source `source` that may be shown, each with
+/// the candidate for its name. Files used by declared tables are left out.
+fn scan_entries(scan: &mut Scan, source: usize, dir: &Path) -> (Vec<Candidate>, Vec<Scanned>) {
+    let mut candidates = Vec::new();
+    let mut scanned = Vec::new();
+    for entry in list_entries(dir) {
+        match entry {
+            CatalogEntry::File {
+                path,
+                stem,
+                extension,
+                ..
+            } => {
+                // Files that are still being written show up once their footer is in place
+                if scan.declared_files.contains(&path) || !is_complete(&path, &mut scan.warnings) {
+                    continue;
+                }
+                scanned.push(Scanned::File(path.clone()));
+                candidates.push(Candidate {
+                    path,
+                    stem,
+                    extension: Some(extension),
+                });
+            }
+            // A directory of part files is a single logical table
+            CatalogEntry::Dataset {
+                path,
+                name,
+                mut parts,
+            } => {
+                parts.retain(|part| !scan.declared_files.contains(part));
+                if parts.is_empty() {
+                    continue;
+                }
+                scanned.push(Scanned::Dataset(parts));
+                candidates.push(Candidate {
+                    path,
+                    stem: name,
+                    extension: None,
+                });
+            }
+            CatalogEntry::Directory { path, name } => {
+                scanned.push(Scanned::Directory(vec![(source, path.clone())]));
+                candidates.push(Candidate {
+                    path,
+                    stem: name,
+                    extension: None,
+                });
+            }
+        }
+    }
+    (candidates, scanned)
+}
@@ -0,0 +1,109 @@
+use std::path::PathBuf;
+use std::str::FromStr;
+
+/// A source directory and the name it has in the mount.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Source {
+    /// The name of the source's top-level directory when sources are mounted as namespaces.
+    pub name: String,
+    pub path: PathBuf,
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    /// Parses a `name=path` pair, such as `raw=/mnt/volume1/raw`.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let Some((name, path)) = value.split_once('=') else {
+            return Err(format!("Invalid source '{value}', expected NAME=PATH"));
+        };
+        if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+            return Err(format!(
+                "Invalid source name '{name}', expected a name without slashes"
+            ));
+        }
+        if path.is_empty() {
+            return Err(format!("Source '{name}' has no path"));
+        }
+        Ok(Source {
+            name: name.to_string(),
+            path: PathBuf::from(path),
+        })
+    }
+}
+
+/// What happens to tables found at the same path in several overlaid sources.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// The table of the source given first is shown and the others are left out.
+    #[default]
+    First,
+    /// Every conflicting table gets the name of its source appended with a dash, as in
+    /// `sales-raw` and `sales-curated`.
+    Suffix,
+    /// Conflicts are refused. Mounting fails, and tables that start to conflict while mounted
+    /// are left out except for the first one.
+    Error,
+}
+
+impl FromStr for ConflictPolicy {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "first" => Ok(ConflictPolicy::First),
+            "suffix" => Ok(ConflictPolicy::Suffix),
+            "error" => Ok(ConflictPolicy::Error),
+            _ => Err(format!(
+                "Unknown conflict policy '{value}', expected first, suffix or error"
+            )),
+        }
+    }
+}
+
+/// How several sources are combined into one tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layout {
+    /// Each source is a top-level directory named after it.
+    Namespaces,
+    /// The sources are merged into one tree. Directories found in several sources are merged
+    /// and tables found in several sources are resolved with the policy.
+    Overlay(ConflictPolicy),
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout::Overlay(ConflictPolicy::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_source() {
+        assert_eq!(
+            "raw=/data/raw".parse(),
+            Ok(Source {
+                name: "raw".to_string(),
+                path: PathBuf::from("/data/raw"),
+            })
+        );
+        // Only the first `=` separates the name
+        assert_eq!(
+            "q=/data/a=b".parse::<Source>().unwrap().path,
+            PathBuf::from("/data/a=b")
+        );
+        assert!("/data/raw".parse::<Source>().is_err());
+        assert!("a/b=/data".parse::<Source>().is_err());
+        assert!("..=/data".parse::<Source>().is_err());
+        assert!("raw=".parse::<Source>().is_err());
+    }
+
+    #[test]
+    fn test_parse_conflict_policy() {
+        assert_eq!("suffix".parse(), Ok(ConflictPolicy::Suffix));
+        assert!("last".parse::<ConflictPolicy>().is_err());
+    }
+}
@@ -0,0 +1,1609 @@
+use crate::cache::{DEFAULT_CACHE_SIZE, RenderCache};
+use crate::entry::{DirEntry, EntryKind, Stat};
+use crate::inode::{InodeKey, InodeTable};
+use crate::naming::CollisionPolicy;
+use crate::scan::{Scan, ScanEntry, Scanner};
+use crate::source::{Layout, Source};
+use crate::virtual_file::VirtualFile;
+use quiverfs_core::append::{append_rows, parse_rows};
+use quiverfs_core::config::Config;
+use quiverfs_core::filter::Predicate;
+use quiverfs_core::ingest::{TextFormat, parse_text, write_parquet};
+use quiverfs_core::xattr::table_attributes;
+use quiverfs_core::{Dataset, Selection};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::ffi::OsStr;
+use std::os::unix::fs::MetadataExt;
+use std::path::{Component, Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const ROOT_INO: u64 = 1;
+
+/// The largest upload or append file accepted, as they are buffered in memory until they are
+/// closed. Writing past it fails with `EFBIG`.
+pub const MAX_UPLOAD_SIZE: u64 = 1 << 30;
+
+/// Ownership and timestamps of the source file or directory behind a node.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SourceMeta {
+    pub uid: u32,
+    pub gid: u32,
+    pub atime: SystemTime,
+    pub mtime: SystemTime,
+    pub ctime: SystemTime,
+}
+
+impl SourceMeta {
+    /// Reads the metadata of `path`, falling back to the mounting user and the epoch when the
+    /// path cannot be read.
+    pub(crate) fn of(path: &Path) -> Self {
+        let Ok(meta) = path.metadata() else {
+            // SAFETY: getuid and getgid cannot fail
+            let (uid, gid) = unsafe { (libc::getuid(), libc::getgid()) };
+            return SourceMeta {
+                uid,
+                gid,
+                atime: UNIX_EPOCH,
+                mtime: UNIX_EPOCH,
+                ctime: UNIX_EPOCH,
+            };
+        };
+        let ctime =
+            UNIX_EPOCH + Duration::new(meta.ctime().max(0) as u64, meta.ctime_nsec().max(0) as u32);
+        SourceMeta {
+            uid: meta.uid(),
+            gid: meta.gid(),
+            atime: meta.accessed().unwrap_or(UNIX_EPOCH),
+            mtime: meta.modified().unwrap_or(UNIX_EPOCH),
+            ctime,
+        }
+    }
+
+    /// Combines the metadata of a table's parts, keeping the owner of the table root and the
+    /// newest timestamps so that tools comparing mtimes see any change to any part. Declared
+    /// tables have nothing at their root and take the owner of their newest part.
+    fn of_dataset(dataset: &Dataset) -> Self {
+        let parts: Vec<SourceMeta> = dataset.parts.iter().map(|p| SourceMeta::of(p)).collect();
+        let newest = parts.iter().max_by_key(|part| part.mtime);
+        let mut meta = match newest {
+            Some(newest) if !dataset.root.exists() => *newest,
+            _ => SourceMeta::of(&dataset.root),
+        };
+        for part in parts {
+            meta.atime = meta.atime.max(part.atime);
+            meta.mtime = meta.mtime.max(part.mtime);
+            meta.ctime = meta.ctime.max(part.ctime);
+        }
+        meta
+    }
+}
+
+/// A table together with the size and modification time of each part when it was scanned,
+/// used to tell whether rendered contents are still current after a rescan.
+pub struct TableSource {
+    pub dataset: Dataset,
+    pub meta: SourceMeta,
+    stamps: Vec<(u64, Option<SystemTime>)>,
+    attributes: OnceLock<Result<BTreeMap<String, String>, String>>,
+    stats: OnceLock<Result<Vec<u8>, String>>,
+}
+
+impl TableSource {
+    pub(crate) fn new(dataset: Dataset) -> Self {
+        let stamps = dataset
+            .parts
+            .iter()
+            .map(|part| match part.metadata() {
+                Ok(meta) => (meta.len(), meta.modified().ok()),
+                Err(_) => (0, None),
+            })
+            .collect();
+        let meta = SourceMeta::of_dataset(&dataset);
+        TableSource {
+            dataset,
+            meta,
+            stamps,
+            attributes: OnceLock::new(),
+            stats: OnceLock::new(),
+        }
+    }
+
+    /// Returns the extended attributes of the table, reading them on first use.
+    pub fn attributes(&self) -> Result<&BTreeMap<String, String>, String> {
+        self.attributes
+            .get_or_init(|| table_attributes(&self.dataset))
+            .as_ref()
+            .map_err(Clone::clone)
+    }
+
+    /// Returns the contents of the table's `stats.json`, computing them on first use. They
+    /// are kept for as long as the table does not change rather than in the render cache, as
+    /// Arrow IPC tables are read in full to compute them.
+    pub fn stats(&self) -> Result<&[u8], String> {
+        self.stats
+            .get_or_init(|| VirtualFile::Stats.render(&self.dataset, &Selection::All))
+            .as_deref()
+            .map_err(Clone::clone)
+    }
+
+    /// Takes over the statistics computed for `old`, the same table as found by an earlier scan.
+    fn keep_stats(&self, old: &TableSource) {
+        if let Some(stats) = old.stats.get() {
+            let _ = self.stats.set(stats.clone());
+        }
+    }
+
+    fn same_as(&self, other: &TableSource) -> bool {
+        self.dataset.parts == other.dataset.parts
+            && self.dataset.view == other.dataset.view
+            && self.stamps == other.stamps
+    }
+}
+
+pub enum NodeKind {
+    Root,
+    /// The top-level directory of a source when sources are mounted as namespaces.
+    Namespace {
+        source: usize,
+    },
+    Directory,
+    Table {
+        source: Arc<TableSource>,
+    },
+    /// A table's `where` directory, or a directory below it holding the rows that match
+    /// `predicates`.
+    Filter {
+        source: Arc<TableSource>,
+        predicates: Vec<Predicate>,
+    },
+    /// A table's `rows` directory, holding row-range files created on first lookup.
+    Rows {
+        source: Arc<TableSource>,
+    },
+    File {
+        source: Arc<TableSource>,
+        selection: Selection,
+        file: VirtualFile,
+    },
+    /// A CSV or JSON file being copied into the mount root in read-write mode. Its contents
+    /// are buffered until it is flushed, when they are converted to a Parquet table in `dir`.
+    Upload {
+        format: TextFormat,
+        dirty: bool,
+        dir: PathBuf,
+    },
+    /// A table's `append.csv` or `append.jsonl` in read-write mode. Rows written to it are
+    /// buffered until it is flushed, when they are added to the table.
+    Append {
+        source: Arc<TableSource>,
+        format: TextFormat,
+    },
+    /// A table's `append.errors`, explaining why the last append to it was rejected.
+    AppendErrors {
+        source: Arc<TableSource>,
+    },
+}
+
+impl NodeKind {
+    fn entry_kind(&self) -> EntryKind {
+        if self.is_dir() {
+            EntryKind::Directory
+        } else {
+            EntryKind::File
+        }
+    }
+
+    pub fn is_dir(&self) -> bool {
+        !matches!(
+            self,
+            NodeKind::File { .. }
+                | NodeKind::Upload { .. }
+                | NodeKind::Append { .. }
+                | NodeKind::AppendErrors { .. }
+        )
+    }
+}
+
+pub struct Node {
+    pub parent: u64,
+    pub name: String,
+    pub kind: NodeKind,
+    pub meta: SourceMeta,
+    pub children: Vec<u64>,
+}
+
+/// A kernel cache entry made stale by a rescan.
+pub enum Invalidation {
+    /// The directory entry `name` under `parent` was added or removed.
+    Entry { parent: u64, name: String },
+    /// The attributes or contents of an inode changed.
+    Inode(u64),
+}
+
+/// The directory tree presented by the mount, built from a scan of the source directories.
+pub struct Tree {
+    sources: Vec<Source>,
+    layout: Layout,
+    nodes: BTreeMap<u64, Node>,
+    inodes: InodeTable,
+    contents: HashMap<u64, Vec<u8>>, // buffered uploads and appends, by inode
+    rendered: RenderCache,
+    sizes: HashMap<u64, u64>, // sizes of rendered virtual files, kept after they are dropped
+    read_write: bool,
+    append_errors: HashMap<PathBuf, String>, // last rejected append, by table root
+    collision_policy: CollisionPolicy,
+    config: Config,
+    collisions: Vec<String>,   // entries left out by the last scan
+    conflicts: Vec<String>,    // tables of overlaid sources left out by the last scan
+    warnings: Vec<String>,     // problems found by the scan in progress
+    reported: HashSet<String>, // problems found by the last scan, already logged
+}
+
+impl Tree {
+    pub fn new(source_dir: PathBuf) -> Self {
+        let source = Source {
+            name: String::new(),
+            path: source_dir,
+        };
+        Tree::with_sources(vec![source], Layout::default())
+    }
+
+    /// Builds a tree from several source directories, combined according to `layout`. The
+    /// sources must not contain one another, since entries are identified by their path.
+    ///
+    /// Uploads go to the first source when the sources are overlaid.
+    pub fn with_sources(sources: Vec<Source>, layout: Layout) -> Self {
+        assert!(!sources.is_empty(), "a tree needs at least one source");
+        let mut tree = Tree {
+            sources,
+            layout,
+            nodes: BTreeMap::new(),
+            inodes: InodeTable::default(),
+            contents: HashMap::new(),
+            rendered: RenderCache::new(DEFAULT_CACHE_SIZE),
+            sizes: HashMap::new(),
+            read_write: false,
+            append_errors: HashMap::new(),
+            collision_policy: CollisionPolicy::default(),
+            config: Config::default(),
+            collisions: Vec::new(),
+            conflicts: Vec::new(),
+            warnings: Vec::new(),
+            reported: HashSet::new(),
+        };
+        let scan = tree.scanner().scan();
+        tree.build(scan);
+        tree
+    }
+
+    pub fn sources(&self) -> &[Source] {
+        &self.sources
+    }
+
+    pub fn layout(&self) -> Layout {
+        self.layout
+    }
+
+    /// The directory shown at the root, whose owner and timestamps the root reports.
+    fn root_dir(&self) -> &Path {
+        &self.sources[0].path
+    }
+
+    /// Shows or hides the files that only exist in read-write mounts, such as the
+    /// `append.csv` of each table.
+    pub fn set_read_write(&mut self, read_write: bool) {
+        self.read_write = read_write;
+        self.rescan();
+    }
+
+    /// Changes how tables whose names collide are named and rescans.
+    pub fn set_collision_policy(&mut self, policy: CollisionPolicy) {
+        self.collision_policy = policy;
+        self.rescan();
+    }
+
+    /// Declares tables made of the files matching patterns, with columns hidden, renamed or
+    /// converted, and rescans. Declared tables are shown at the top of each source and take
+    /// precedence over the tables found by the scan, which skips the files they use.
+    pub fn set_config(&mut self, config: Config) {
+        self.config = config;
+        self.rescan();
+    }
+
+    /// Describes the entries that the last scan left out because their name was taken.
+    pub fn collisions(&self) -> &[String] {
+        &self.collisions
+    }
+
+    /// Describes the tables of overlaid sources that the last scan left out because another
+    /// source has a table at the same path.
+    pub fn conflicts(&self) -> &[String] {
+        &self.conflicts
+    }
+
+    /// Returns the settings to scan the source directories with, for scanning them without
+    /// holding the tree before passing the result to [`Tree::update`].
+    pub fn scanner(&self) -> Scanner {
+        Scanner {
+            sources: self.sources.clone(),
+            layout: self.layout,
+            collision_policy: self.collision_policy,
+            config: self.config.clone(),
+        }
+    }
+
+    fn build(&mut self, scan: Scan) {
+        self.collisions = scan.collisions;
+        self.conflicts = scan.conflicts;
+        self.warnings = scan.warnings;
+        self.nodes.insert(
+            ROOT_INO,
+            Node {
+                parent: ROOT_INO,
+                name: String::new(),
+                kind: NodeKind::Root,
+                meta: scan.root,
+                children: Vec::new(),
+            },
+        );
+        self.add_entries(ROOT_INO, scan.entries);
+        self.report_warnings();
+    }
+
+    fn add_entries(&mut self, parent: u64, entries: Vec<ScanEntry>) {
+        for entry in entries {
+            match entry {
+                ScanEntry::Namespace {
+                    source,
+                    name,
+                    path,
+                    meta,
+                    entries,
+                } => {
+                    let key = InodeKey::source(&path);
+                    let kind = NodeKind::Namespace { source };
+                    let ino = self.add_keyed_node(parent, name, kind, meta, key);
+                    self.add_entries(ino, entries);
+                }
+                ScanEntry::Directory {
+                    name,
+                    path,
+                    meta,
+                    entries,
+                } => {
+                    let key = InodeKey::source(&path);
+                    let ino = self.add_keyed_node(parent, name, NodeKind::Directory, meta, key);
+                    self.add_entries(ino, entries);
+                }
+                ScanEntry::Table(source) => self.add_table(parent, source),
+            }
+        }
+    }
+
+    /// Logs the problems found by the scan that the previous scan did not find, so that a
+    /// problem is reported once rather than on every rescan.
+    fn report_warnings(&mut self) {
+        let warnings = std::mem::take(&mut self.warnings);
+        for warning in &warnings {
+            if !self.reported.contains(warning) {
+                log::warn!("{warning}");
+            }
+        }
+        self.reported = warnings.into_iter().collect();
+    }
+
+    /// Rebuilds the tree from the source directories, keeping the inode numbers of entries that
+    /// still exist, and returns the kernel cache entries that became stale.
+    pub fn rescan(&mut self) -> Vec<Invalidation> {
+        let scan = self.scanner().scan();
+        self.update(scan)
+    }
+
+    /// Rebuilds the tree from a scan made with [`Tree::scanner`], as [`Tree::rescan`] does.
+    pub fn update(&mut self, scan: Scan) -> Vec<Invalidation> {
+        let mut old_nodes = std::mem::take(&mut self.nodes);
+        let mut old_contents = std::mem::take(&mut self.contents);
+        let uploads: Vec<u64> = old_nodes
+            .iter()
+            .filter(|(_, node)| matches!(node.kind, NodeKind::Upload { .. }))
+            .map(|(ino, _)| *ino)
+            .collect();
+        self.build(scan);
+        // Uploads in progress have no source file yet and are carried over as they are
+        for ino in uploads {
+            if let Some(node) = old_nodes.remove(&ino) {
+                if let Some(parent) = self.nodes.get_mut(&node.parent) {
+                    parent.children.push(ino);
+                }
+                self.nodes.insert(ino, node);
+            }
+            if let Some(data) = old_contents.remove(&ino) {
+                self.contents.insert(ino, data);
+            }
+        }
+        // Filter directories and row ranges only exist once looked up, so recreate the ones
+        // still in use. Parents have lower inode numbers than their children and are recreated
+        // first.
+        for old in old_nodes.values() {
+            let looked_up = match &old.kind {
+                NodeKind::Filter { predicates, .. } => !predicates.is_empty(),
+                NodeKind::File { selection, .. } => matches!(selection, Selection::Rows(_)),
+                _ => false,
+            };
+            if looked_up {
+                let _ = self.lookup(old.parent, &old.name);
+            }
+        }
+
+        let mut stale = Vec::new();
+        let mut current = HashSet::new(); // rendered files whose table did not change
+        for (ino, old) in &old_nodes {
+            let Some(new) = self.nodes.get(ino) else {
+                stale.push(Invalidation::Entry {
+                    parent: old.parent,
+                    name: old.name.clone(),
+                });
+                continue;
+            };
+            // Tables keep their inode when they are renamed, as when another table starts to
+            // collide with them
+            if (old.parent, &old.name) != (new.parent, &new.name) {
+                stale.push(Invalidation::Entry {
+                    parent: old.parent,
+                    name: old.name.clone(),
+                });
+                stale.push(Invalidation::Entry {
+                    parent: new.parent,
+                    name: new.name.clone(),
+                });
+            }
+            match (&old.kind, &new.kind) {
+                (
+                    NodeKind::File {
+                        source: old_src, ..
+                    },
+                    NodeKind::File {
+                        source: new_src, ..
+                    },
+                ) if old_src.same_as(new_src) => {
+                    new_src.keep_stats(old_src);
+                    current.insert(*ino);
+                }
+                (NodeKind::File { .. }, _) => stale.push(Invalidation::Inode(*ino)),
+                // Rows being written to an append file are kept until it is flushed
+                (NodeKind::Append { .. }, NodeKind::Append { .. }) => {
+                    if let Some(data) = old_contents.get(ino) {
+                        self.contents.insert(*ino, data.clone());
+                    }
+                }
+                _ if old.children != new.children => stale.push(Invalidation::Inode(*ino)),
+                _ => {}
+            }
+        }
+        // New entries may be shadowed by cached negative lookups
+        for (ino, new) in &self.nodes {
+            if !old_nodes.contains_key(ino) {
+                stale.push(Invalidation::Entry {
+                    parent: new.parent,
+                    name: new.name.clone(),
+                });
+            }
+        }
+        self.rendered.retain(|ino| current.contains(&ino));
+        self.sizes.retain(|ino, _| current.contains(ino));
+        let nodes = &self.nodes;
+        self.inodes
+            .free_unreferenced(|ino| nodes.contains_key(&ino));
+        stale
+    }
+
+    /// Adds a table directory holding its schema and statistics, its whole-table views and
+    /// re-encodings, one text file per top-level column, a `where` directory for filtering
+    /// rows, a `rows` directory for slicing them and, for Parquet tables, a `row_groups`
+    /// directory. Read-write mounts also get files for appending rows.
+    fn add_table(&mut self, parent: u64, source: Arc<TableSource>) {
+        self.warnings
+            .extend(source.dataset.diagnostics.iter().cloned());
+        let name = source.dataset.name.clone();
+        let kind = NodeKind::Table {
+            source: source.clone(),
+        };
+        let table = self.add_node(parent, name, kind, source.meta);
+
+        let schema = NodeKind::File {
+            source: source.clone(),
+            selection: Selection::All,
+            file: VirtualFile::Schema,
+        };
+        self.add_node(table, "schema.json".to_string(), schema, source.meta);
+        // Arrow IPC tables are read in full to compute their statistics, which are kept with
+        // the table
+        let stats = NodeKind::File {
+            source: source.clone(),
+            selection: Selection::All,
+            file: VirtualFile::Stats,
+        };
+        self.add_node(table, "stats.json".to_string(), stats, source.meta);
+        self.add_data_files(table, &source, &Selection::All);
+        for (name, file) in [
+            ("as.parquet", VirtualFile::Parquet),
+            ("as.arrow", VirtualFile::Arrow),
+        ] {
+            let kind = NodeKind::File {
+                source: source.clone(),
+                selection: Selection::All,
+                file,
+            };
+            self.add_node(table, name.to_string(), kind, source.meta);
+        }
+        let filter = NodeKind::Filter {
+            source: source.clone(),
+            predicates: Vec::new(),
+        };
+        self.add_node(table, "where".to_string(), filter, source.meta);
+        let rows = NodeKind::Rows {
+            source: source.clone(),
+        };
+        self.add_node(table, "rows".to_string(), rows, source.meta);
+
+        // Declared tables are made of files picked by patterns, and their columns may not be
+        // the ones of the files, so rows cannot be appended to them
+        if self.read_write && source.dataset.schema.is_some() && source.dataset.view.is_none() {
+            for (name, format) in [
+                ("append.csv", TextFormat::Csv),
+                ("append.jsonl", TextFormat::Jsonl),
+            ] {
+                let kind = NodeKind::Append {
+                    source: source.clone(),
+                    format,
+                };
+                self.add_node(table, name.to_string(), kind, source.meta);
+            }
+            let errors = NodeKind::AppendErrors {
+                source: source.clone(),
+            };
+            self.add_node(table, "append.errors".to_string(), errors, source.meta);
+        }
+
+        let row_groups = source.dataset.row_groups().map_or(0, |groups| groups.len());
+        if row_groups > 0 {
+            let dir = self.add_node(
+                table,
+                "row_groups".to_string(),
+                NodeKind::Directory,
+                source.meta,
+            );
+            for index in 0..row_groups {
+                let group = self.add_node(dir, index.to_string(), NodeKind::Directory, source.meta);
+                let metadata = NodeKind::File {
+                    source: source.clone(),
+                    selection: Selection::RowGroup(index),
+                    file: VirtualFile::RowGroupMetadata { index },
+                };
+                self.add_node(group, "metadata.json".to_string(), metadata, source.meta);
+                self.add_data_files(group, &source, &Selection::RowGroup(index));
+            }
+        }
+    }
+
+    /// Adds the data views and column files of a table under `dir`, showing the rows picked by
+    /// `selection`.
+    fn add_data_files(&mut self, dir: u64, source: &Arc<TableSource>, selection: &Selection) {
+        let mut files = vec![
+            ("data.csv".to_string(), VirtualFile::Csv),
+            ("data.jsonl".to_string(), VirtualFile::Jsonl),
+        ];
+        // An unreadable footer leaves the table without column files rather than hiding it.
+        if let Some(schema) = source.dataset.output_schema() {
+            let mut taken: HashSet<String> = files.iter().map(|(name, _)| name.clone()).collect();
+            for (index, field) in schema.fields().iter().enumerate() {
+                let stem = field.name().replace('/', "_");
+                let mut name = format!("{stem}.txt");
+                // Columns whose names only differ by a slash, or that repeat a name, are told
+                // apart by their position
+                let mut suffix = index;
+                while taken.contains(&name) {
+                    name = format!("{stem}-{suffix}.txt");
+                    suffix += 1;
+                }
+                if name != format!("{stem}.txt") && *selection == Selection::All {
+                    self.warnings.push(format!(
+                        "column '{}' of table '{}' is shown as '{}' because '{}.txt' is taken",
+                        field.name(),
+                        source.dataset.name,
+                        name,
+                        stem
+                    ));
+                }
+                taken.insert(name.clone());
+                files.push((name, VirtualFile::Column { index }));
+            }
+        }
+        for (name, file) in files {
+            let kind = NodeKind::File {
+                source: source.clone(),
+                selection: selection.clone(),
+                file,
+            };
+            self.add_node(dir, name, kind, source.meta);
+        }
+    }
+
+    fn key(&self, ino: u64) -> InodeKey {
+        match self.inodes.key(ino) {
+            Some(key) if ino != ROOT_INO => key.clone(),
+            _ => InodeKey::source(self.root_dir()),
+        }
+    }
+
+    /// Adds a node below `parent`. Tables are keyed by their source path and other entries by
+    /// their path below their parent's key, so that inode numbers only depend on what a node
+    /// shows and not on the order it was found in or on how colliding tables are named.
+    fn add_node(&mut self, parent: u64, name: String, kind: NodeKind, meta: SourceMeta) -> u64 {
+        let key = match &kind {
+            NodeKind::Table { source } => InodeKey::source(&source.dataset.root),
+            _ => self.key(parent).child(&name),
+        };
+        self.add_keyed_node(parent, name, kind, meta, key)
+    }
+
+    fn add_keyed_node(
+        &mut self,
+        parent: u64,
+        name: String,
+        kind: NodeKind,
+        meta: SourceMeta,
+        key: InodeKey,
+    ) -> u64 {
+        let ino = self.inodes.assign(key);
+        self.nodes.insert(
+            ino,
+            Node {
+                parent,
+                name,
+                kind,
+                meta,
+                children: Vec::new(),
+            },
+        );
+        if let Some(parent) = self.nodes.get_mut(&parent) {
+            parent.children.push(ino);
+        }
+        ino
+    }
+
+    fn remove_node(&mut self, ino: u64) {
+        if let Some(node) = self.nodes.remove(&ino)
+            && let Some(parent) = self.nodes.get_mut(&node.parent)
+        {
+            parent.children.retain(|child| *child != ino);
+        }
+    }
+
+    fn node(&self, ino: u64) -> Option<&Node> {
+        self.nodes.get(&ino)
+    }
+
+    fn get_child_by_name(&self, parent: u64, name: &str) -> Option<u64> {
+        self.nodes
+            .get(&parent)?
+            .children
+            .iter()
+            .copied()
+            .find(|ino| self.nodes.get(ino).is_some_and(|node| node.name == name))
+    }
+
+    /// Finds the entry `name` under `parent`, creating the entries of `where` and `rows`
+    /// directories on first lookup.
+    pub fn lookup(&mut self, parent: u64, name: &str) -> Result<u64, libc::c_int> {
+        if let Some(ino) = self.get_child_by_name(parent, name) {
+            return Ok(ino);
+        }
+        match self.nodes.get(&parent).map(|n| &n.kind) {
+            Some(NodeKind::Filter { .. }) => self.add_filter(parent, name),
+            Some(NodeKind::Rows { .. }) => self.add_row_range(parent, name),
+            _ => Err(libc::ENOENT),
+        }
+    }
+
+    /// Finds the entry at `path`, relative to the root of the tree, by looking up each of its
+    /// components in turn as the kernel does.
+    pub fn resolve(&mut self, path: &Path) -> Result<u64, libc::c_int> {
+        let mut ino = ROOT_INO;
+        for component in path.components() {
+            let Some(node) = self.nodes.get(&ino) else {
+                return Err(libc::ENOENT);
+            };
+            ino = match component {
+                Component::RootDir | Component::CurDir => ino,
+                Component::ParentDir => node.parent,
+                Component::Normal(_) if !node.kind.is_dir() => return Err(libc::ENOTDIR),
+                Component::Normal(name) => {
+                    let name = name.to_str().ok_or(libc::ENOENT)?;
+                    self.lookup(ino, name)?
+                }
+                Component::Prefix(_) => return Err(libc::ENOENT),
+            };
+        }
+        Ok(ino)
+    }
+
+    /// Lists the entries of a directory, without `.` and `..`.
+    ///
+    /// The entries of `where` and `rows` directories only appear once they are looked up.
+    pub fn list(&self, ino: u64) -> Result<Vec<DirEntry>, libc::c_int> {
+        let dir = self.node(ino).ok_or(libc::ENOENT)?;
+        if !dir.kind.is_dir() {
+            return Err(libc::ENOTDIR);
+        }
+        Ok(dir
+            .children
+            .iter()
+            .filter_map(|child| {
+                let node = self.node(*child)?;
+                Some(DirEntry {
+                    ino: *child,
+                    kind: node.kind.entry_kind(),
+                    name: node.name.clone(),
+                })
+            })
+            .collect())
+    }
+
+    /// Records that the kernel was given a reference to `ino`, such as by replying to a
+    /// lookup, which it gives back through [`Tree::forget`].
+    pub fn remember(&mut self, ino: u64) {
+        self.inodes.remember(ino);
+    }
+
+    /// Drops `count` of the kernel's references to `ino`. The inode number of an entry that
+    /// no longer exists is freed once nothing refers to it.
+    pub fn forget(&mut self, ino: u64, count: u64) {
+        let exists = self.nodes.contains_key(&ino);
+        self.inodes.forget(ino, count, exists);
+    }
+
+    /// Returns the parent of an entry. The root is its own parent.
+    pub fn parent(&self, ino: u64) -> Result<u64, libc::c_int> {
+        self.node(ino).map(|node| node.parent).ok_or(libc::ENOENT)
+    }
+
+    /// Describes an entry. Virtual files are rendered to tell their size the first time it is
+    /// asked for, and their size is kept until their table changes.
+    pub fn stat(&mut self, ino: u64) -> Result<Stat, libc::c_int> {
+        let node = self.node(ino).ok_or(libc::ENOENT)?;
+        let (meta, kind) = (node.meta, node.kind.entry_kind());
+        let writable = matches!(node.kind, NodeKind::Upload { .. } | NodeKind::Append { .. });
+        // Each subdirectory links back to its parent through `..`
+        let subdirs = node
+            .children
+            .iter()
+            .filter(|child| self.node(**child).is_some_and(|c| c.kind.is_dir()))
+            .count() as u32;
+        let size = match &node.kind {
+            NodeKind::Upload { .. } | NodeKind::Append { .. } => {
+                self.contents.get(&ino).map_or(0, Vec::len) as u64
+            }
+            NodeKind::AppendErrors { source } => self
+                .append_errors
+                .get(&source.dataset.root)
+                .map_or(0, String::len) as u64,
+            NodeKind::File { .. } => match self.sizes.get(&ino) {
+                Some(size) => *size,
+                None => self.content(ino)?.len() as u64,
+            },
+            _ => 0,
+        };
+        let (perm, nlink) = match kind {
+            EntryKind::Directory => (0o755, 2 + subdirs),
+            EntryKind::File if writable => (0o644, 1),
+            EntryKind::File => (0o444, 1),
+        };
+        Ok(Stat {
+            ino,
+            generation: self.inodes.generation(ino),
+            kind,
+            size,
+            perm,
+            nlink,
+            uid: meta.uid,
+            gid: meta.gid,
+            atime: meta.atime,
+            mtime: meta.mtime,
+            ctime: meta.ctime,
+        })
+    }
+
+    /// Checks that a file can be opened, for writing when `write` is set, and renders its
+    /// contents so that errors are reported when it is opened rather than when it is read.
+    /// The contents of the files of a table are kept in memory until they are released.
+    ///
+    /// Fails with `EACCES` when writing to a read-only file and with `EISDIR` for directories.
+    pub fn open(&mut self, ino: u64, write: bool) -> Result<(), libc::c_int> {
+        match self.node(ino).map(|n| &n.kind) {
+            Some(NodeKind::Upload { .. } | NodeKind::Append { .. }) => {}
+            Some(NodeKind::File { .. } | NodeKind::AppendErrors { .. }) if !write => {}
+            Some(NodeKind::File { .. } | NodeKind::AppendErrors { .. }) => {
+                return Err(libc::EACCES);
+            }
+            Some(_) => return Err(libc::EISDIR),
+            None => return Err(libc::ENOENT),
+        }
+        self.content(ino)?;
+        if matches!(self.node(ino).map(|n| &n.kind), Some(NodeKind::File { .. })) {
+            self.rendered.open(ino);
+        }
+        Ok(())
+    }
+
+    /// Releases a handle to a file once it is closed, dropping it if it is an upload.
+    pub fn release(&mut self, ino: u64) {
+        self.rendered.close(ino);
+        self.remove_upload(ino);
+    }
+
+    /// Reads up to `size` bytes of a file, starting at `offset`. Reads past the end return
+    /// fewer bytes, or none.
+    pub fn read(&mut self, ino: u64, offset: u64, size: usize) -> Result<&[u8], libc::c_int> {
+        if self.node(ino).is_some_and(|node| node.kind.is_dir()) {
+            return Err(libc::EISDIR);
+        }
+        let data = self.content(ino)?;
+        let start = (offset as usize).min(data.len());
+        let end = start.saturating_add(size).min(data.len());
+        Ok(&data[start..end])
+    }
+
+    /// Adds a directory such as `country=DE` or `amount>100` below a `where` directory,
+    /// holding the rows that match its predicate and those of the directories above it.
+    ///
+    /// Fails with `ENOENT` for names that are not predicates on a known column, and with
+    /// `EINVAL` for values that cannot be compared with the column.
+    fn add_filter(&mut self, parent: u64, name: &str) -> Result<u64, libc::c_int> {
+        let Some(NodeKind::Filter { source, predicates }) =
+            self.nodes.get(&parent).map(|n| &n.kind)
+        else {
+            return Err(libc::ENOENT);
+        };
+        let predicate = Predicate::parse(name).map_err(|_| libc::ENOENT)?;
+        let Some(schema) = source.dataset.output_schema() else {
+            return Err(libc::ENOENT);
+        };
+        if schema.field_with_name(&predicate.column).is_err() {
+            return Err(libc::ENOENT);
+        }
+        predicate.validate(&schema).map_err(|_| libc::EINVAL)?;
+
+        let source = source.clone();
+        let mut predicates = predicates.clone();
+        predicates.push(predicate);
+        let kind = NodeKind::Filter {
+            source: source.clone(),
+            predicates: predicates.clone(),
+        };
+        let dir = self.add_node(parent, name.to_string(), kind, source.meta);
+        self.add_data_files(dir, &source, &Selection::Where(predicates));
+        Ok(dir)
+    }
+
+    /// Adds a file such as `0-9999.csv` or `50000-50100.jsonl` below a `rows` directory,
+    /// holding the rows between the two positions, both included.
+    ///
+    /// Fails with `ENOENT` for other names and `EINVAL` for ranges that end before they start.
+    fn add_row_range(&mut self, parent: u64, name: &str) -> Result<u64, libc::c_int> {
+        let Some(NodeKind::Rows { source }) = self.nodes.get(&parent).map(|n| &n.kind) else {
+            return Err(libc::ENOENT);
+        };
+        let (range, file) = if let Some(range) = name.strip_suffix(".csv") {
+            (range, VirtualFile::Csv)
+        } else if let Some(range) = name.strip_suffix(".jsonl") {
+            (range, VirtualFile::Jsonl)
+        } else {
+            return Err(libc::ENOENT);
+        };
+        let Some((Ok(first), Ok(last))) = range
+            .split_once('-')
+            .map(|(first, last)| (first.parse::<usize>(), last.parse::<usize>()))
+        else {
+            return Err(libc::ENOENT);
+        };
+        if last < first {
+            return Err(libc::EINVAL);
+        }
+
+        let source = source.clone();
+        let kind = NodeKind::File {
+            selection: Selection::Rows(first..last.saturating_add(1)),
+            source: source.clone(),
+            file,
+        };
+        Ok(self.add_node(parent, name.to_string(), kind, source.meta))
+    }
+
+    /// Starts an upload of a `.csv` or `.jsonl` file named `name` into the mount root, or into
+    /// the top-level directory of a source when sources are mounted as namespaces. Uploads to
+    /// the root of overlaid sources become tables of the first source.
+    ///
+    /// Fails with `EROFS` unless the tree is read-write, `EACCES` outside those directories,
+    /// `ENOTSUP` for other file types and `EEXIST` if the name or the table it would become is
+    /// already taken.
+    pub fn create_upload(&mut self, parent: u64, name: &str) -> Result<u64, libc::c_int> {
+        if !self.read_write {
+            return Err(libc::EROFS);
+        }
+        let dir = match (&self.node(parent).ok_or(libc::ENOENT)?.kind, self.layout) {
+            (NodeKind::Root, Layout::Overlay(_)) => self.sources[0].path.clone(),
+            (NodeKind::Namespace { source }, _) => self.sources[*source].path.clone(),
+            _ => return Err(libc::EACCES),
+        };
+        let format = TextFormat::from_path(name).ok_or(libc::ENOTSUP)?;
+        let stem = Path::new(name)
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .ok_or(libc::EINVAL)?;
+        if self.get_child_by_name(parent, name).is_some()
+            || self.get_child_by_name(parent, stem).is_some()
+            || upload_destination(&dir, stem).exists()
+        {
+            return Err(libc::EEXIST);
+        }
+
+        let mut meta = SourceMeta::of(&dir);
+        let now = SystemTime::now();
+        (meta.atime, meta.mtime, meta.ctime) = (now, now, now);
+        let kind = NodeKind::Upload {
+            format,
+            dirty: false,
+            dir,
+        };
+        let ino = self.add_node(parent, name.to_string(), kind, meta);
+        self.contents.insert(ino, Vec::new());
+        Ok(ino)
+    }
+
+    /// Gives mutable access to the buffered contents of an upload or append file, marking it
+    /// as changed.
+    fn upload_buffer(&mut self, ino: u64) -> Result<&mut Vec<u8>, libc::c_int> {
+        match self.nodes.get_mut(&ino).map(|n| &mut n.kind) {
+            Some(NodeKind::Upload { dirty, .. }) => *dirty = true,
+            Some(NodeKind::Append { .. }) => {}
+            Some(_) => return Err(libc::EACCES),
+            None => return Err(libc::ENOENT),
+        }
+        if let Some(node) = self.nodes.get_mut(&ino) {
+            node.meta.mtime = SystemTime::now();
+        }
+        Ok(self.contents.entry(ino).or_default())
+    }
+
+    /// Writes `data` at `offset` into an upload or append file, returning the number of bytes written.
+    pub fn write(&mut self, ino: u64, offset: u64, data: &[u8]) -> Result<usize, libc::c_int> {
+        let end = offset
+            .checked_add(data.len() as u64)
+            .filter(|end| *end <= MAX_UPLOAD_SIZE)
+            .ok_or(libc::EFBIG)?;
+        let buffer = self.upload_buffer(ino)?;
+        let (start, end) = (offset as usize, end as usize);
+        if buffer.len() < end {
+            buffer.resize(end, 0);
+        }
+        buffer[start..end].copy_from_slice(data);
+        Ok(data.len())
+    }
+
+    /// Changes the size of an upload or append file, padding it with zeros when it grows.
+    pub fn truncate(&mut self, ino: u64, size: u64) -> Result<(), libc::c_int> {
+        if size > MAX_UPLOAD_SIZE {
+            return Err(libc::EFBIG);
+        }
+        self.upload_buffer(ino)?.resize(size as usize, 0);
+        Ok(())
+    }
+
+    /// Commits what was written to an upload or append file.
+    pub fn flush(&mut self, ino: u64) -> Result<(), libc::c_int> {
+        match self.nodes.get(&ino).map(|n| &n.kind) {
+            Some(NodeKind::Upload { .. }) => self.ingest(ino),
+            Some(NodeKind::Append { .. }) => self.append(ino),
+            Some(_) => Ok(()),
+            None => Err(libc::ENOENT),
+        }
+    }
+
+    /// Commits every upload and append file that was written to but not yet flushed, such as
+    /// files still open when the filesystem is unmounted. Failures are reported as by
+    /// [`Tree::flush`].
+    pub fn flush_all(&mut self) {
+        let pending: Vec<u64> = self
+            .nodes
+            .iter()
+            .filter(|(ino, node)| match node.kind {
+                NodeKind::Upload { dirty, .. } => dirty,
+                NodeKind::Append { .. } => self.contents.get(ino).is_some_and(|c| !c.is_empty()),
+                _ => false,
+            })
+            .map(|(ino, _)| *ino)
+            .collect();
+        for ino in pending {
+            let _ = self.flush(ino);
+        }
+    }
+
+    /// Converts the contents of an upload written since the last flush into a Parquet file in
+    /// its source directory and rescans, so that the new table appears next to the upload.
+    ///
+    /// Fails with `EINVAL` when the contents cannot be parsed and with `EIO` when the Parquet
+    /// file cannot be written. The reason is reported on standard error.
+    fn ingest(&mut self, ino: u64) -> Result<(), libc::c_int> {
+        let Some(node) = self.nodes.get(&ino) else {
+            return Err(libc::ENOENT);
+        };
+        let NodeKind::Upload {
+            format,
+            dirty,
+            ref dir,
+        } = node.kind
+        else {
+            return Ok(());
+        };
+        if !dirty {
+            return Ok(());
+        }
+        let name = node.name.clone();
+        let stem = Path::new(&name)
+            .file_stem()
+            .and_then(OsStr::to_str)
+            .unwrap_or_default();
+        let dest = upload_destination(dir, stem);
+        let data = self
+            .contents
+            .get(&ino)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+
+        let (schema, batches) = parse_text(data, format).map_err(|e| {
+            log::error!("cannot import {name}: {e}");
+            libc::EINVAL
+        })?;
+        write_parquet(&dest, schema, &batches).map_err(|e| {
+            log::error!("cannot import {name}: {e}");
+            libc::EIO
+        })?;
+        if let Some(NodeKind::Upload { dirty, .. }) = self.nodes.get_mut(&ino).map(|n| &mut n.kind)
+        {
+            *dirty = false;
+        }
+        self.rescan();
+        Ok(())
+    }
+
+    /// Adds the rows written to an append file since the last flush to its table and rescans.
+    ///
+    /// Fails with `EINVAL` when the rows do not match the table's schema and with `EIO` when
+    /// they cannot be written. Either way the rows are discarded and the reason is kept in the
+    /// table's `append.errors` until the next successful append.
+    fn append(&mut self, ino: u64) -> Result<(), libc::c_int> {
+        let Some(NodeKind::Append { source, format }) = self.nodes.get(&ino).map(|n| &n.kind)
+        else {
+            return Err(libc::ENOENT);
+        };
+        let (source, format) = (source.clone(), *format);
+        let data = self.contents.remove(&ino).unwrap_or_default();
+        if data.is_empty() {
+            return Ok(());
+        }
+        let dataset = &source.dataset;
+        let schema = dataset.schema.clone().ok_or(libc::EIO)?;
+
+        let result = parse_rows(&data, format, &schema)
+            .map_err(|e| (e, libc::EINVAL))
+            .and_then(|batches| append_rows(dataset, &batches).map_err(|e| (e, libc::EIO)));
+        match result {
+            Ok(_) => {
+                self.append_errors.remove(&dataset.root);
+                self.rescan();
+                Ok(())
+            }
+            Err((message, errno)) => {
+                log::error!("cannot append to {}: {message}", dataset.name);
+                self.append_errors
+                    .insert(dataset.root.clone(), format!("{message}\n"));
+                Err(errno)
+            }
+        }
+    }
+
+    /// Drops an upload once its file is closed.
+    fn remove_upload(&mut self, ino: u64) {
+        if matches!(
+            self.nodes.get(&ino).map(|n| &n.kind),
+            Some(NodeKind::Upload { .. })
+        ) {
+            self.remove_node(ino);
+            self.contents.remove(&ino);
+        }
+    }
+
+    /// Returns the extended attributes of a node. Only table directories have any.
+    pub fn attributes(&self, ino: u64) -> Result<BTreeMap<String, String>, libc::c_int> {
+        match self.nodes.get(&ino).map(|n| &n.kind) {
+            Some(NodeKind::Table { source }) => source.attributes().cloned().map_err(|e| {
+                log::error!("cannot read metadata of {}: {e}", source.dataset.name);
+                libc::EIO
+            }),
+            Some(_) => Ok(BTreeMap::new()),
+            None => Err(libc::ENOENT),
+        }
+    }
+
+    /// Returns the contents of a file: the buffered contents of uploads and append files, and
+    /// the rendered contents of virtual files, which are generated when they are not cached.
+    fn content(&mut self, ino: u64) -> Result<&[u8], libc::c_int> {
+        let (source, selection, file) = match self.nodes.get(&ino).map(|n| &n.kind) {
+            Some(NodeKind::Upload { .. } | NodeKind::Append { .. }) => {
+                return Ok(self.contents.entry(ino).or_default());
+            }
+            Some(NodeKind::AppendErrors { source }) => {
+                let errors = self.append_errors.get(&source.dataset.root);
+                return Ok(errors.map_or(&[], |errors| errors.as_bytes()));
+            }
+            Some(NodeKind::File {
+                source,
+                selection,
+                file,
+            }) => (source, selection, file),
+            _ => return Err(libc::ENOENT),
+        };
+        if let VirtualFile::Stats = file {
+            let data = source.stats().map_err(|_| libc::EIO)?;
+            self.sizes.insert(ino, data.len() as u64);
+            return Ok(data);
+        }
+        if self.rendered.size(ino).is_none() {
+            let data = file
+                .render(&source.dataset, selection)
+                .map_err(|_| libc::EIO)?;
+            self.sizes.insert(ino, data.len() as u64);
+            return Ok(self.rendered.insert(ino, data));
+        }
+        Ok(self.rendered.get(ino).unwrap_or_default())
+    }
+}
+
+fn upload_destination(dir: &Path, stem: &str) -> PathBuf {
+    dir.join(format!("{stem}.parquet"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::ConflictPolicy;
+    use quiverfs_core::ingest::write_arrow;
+
+    const SALES: &str = "id,country\n1,DE\n2,FR\n3,DE\n";
+
+    /// Writes `csv` as a Parquet table at `path` below `root`.
+    fn write_table(root: &Path, path: &str, csv: &str) {
+        let path = root.join(path);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        let (schema, batches) = parse_text(csv.as_bytes(), TextFormat::Csv).unwrap();
+        write_parquet(&path, schema, &batches).unwrap();
+    }
+
+    fn names(tree: &Tree, ino: u64) -> Vec<String> {
+        let entries = tree.list(ino).unwrap();
+        entries.into_iter().map(|entry| entry.name).collect()
+    }
+
+    fn read_all(tree: &mut Tree, path: &str) -> String {
+        let ino = tree.resolve(Path::new(path)).unwrap();
+        String::from_utf8(tree.read(ino, 0, usize::MAX).unwrap().to_vec()).unwrap()
+    }
+
+    #[test]
+    fn test_layout() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "sales.parquet", SALES);
+        write_table(dir.path(), "events/year=2024/clicks.parquet", SALES);
+        std::fs::create_dir(dir.path().join("empty")).unwrap();
+        let mut tree = Tree::new(dir.path().to_path_buf());
+
+        // Directories without tables are left out
+        assert_eq!(names(&tree, ROOT_INO), ["events", "sales"]);
+        let sales = tree.resolve(Path::new("sales")).unwrap();
+        assert_eq!(
+            names(&tree, sales),
+            [
+                "schema.json",
+                "stats.json",
+                "data.csv",
+                "data.jsonl",
+                "id.txt",
+                "country.txt",
+                "as.parquet",
+                "as.arrow",
+                "where",
+                "rows",
+                "row_groups",
+            ]
+        );
+        let clicks = tree
+            .resolve(Path::new("/events/year=2024/./clicks"))
+            .unwrap();
+        assert_eq!(tree.stat(clicks).unwrap().kind, EntryKind::Directory);
+        let up = tree
+            .resolve(Path::new("events/year=2024/clicks/../.."))
+            .unwrap();
+        assert_eq!(up, tree.resolve(Path::new("events")).unwrap());
+    }
+
+    #[test]
+    fn test_stats_are_kept_with_the_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let (schema, batches) = parse_text(SALES.as_bytes(), TextFormat::Csv).unwrap();
+        write_arrow(&dir.path().join("sales.arrow"), schema, &batches).unwrap();
+        let mut tree = Tree::new(dir.path().to_path_buf());
+        let stats = read_all(&mut tree, "sales/stats.json");
+        assert!(stats.contains("\"num_rows\": 3"));
+
+        // They survive rescans that leave the table unchanged, outside the render cache
+        tree.rescan();
+        let ino = tree.resolve(Path::new("sales/stats.json")).unwrap();
+        assert_eq!(tree.rendered.size(ino), None);
+        let Some(NodeKind::File { source, .. }) = tree.node(ino).map(|n| &n.kind) else {
+            panic!("stats.json is not a file");
+        };
+        assert!(source.stats.get().is_some());
+        assert_eq!(read_all(&mut tree, "sales/stats.json"), stats);
+    }
+
+    #[test]
+    fn test_stat_and_read() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "sales.parquet", SALES);
+        let mut tree = Tree::new(dir.path().to_path_buf());
+
+        let csv = tree.resolve(Path::new("sales/data.csv")).unwrap();
+        let stat = tree.stat(csv).unwrap();
+        assert_eq!(
+            (stat.kind, stat.perm, stat.nlink),
+            (EntryKind::File, 0o444, 1)
+        );
+        assert_eq!(stat.size, SALES.len() as u64);
+        assert_eq!(tree.open(csv, false), Ok(()));
+        assert_eq!(read_all(&mut tree, "sales/data.csv"), SALES);
+        assert_eq!(tree.read(csv, 3, 5).unwrap(), b"count");
+        assert_eq!(tree.read(csv, 1000, 5).unwrap(), b"");
+        assert_eq!(read_all(&mut tree, "sales/country.txt"), "DE\nFR\nDE\n");
+        // The size is remembered once the rendered file is dropped from the cache
+        tree.rendered.remove(csv);
+        assert_eq!(tree.stat(csv).unwrap().size, SALES.len() as u64);
+        assert_eq!(tree.rendered.size(csv), None);
+        // Open files are kept however small the cache is, until they are released
+        tree.rendered = RenderCache::new(1);
+        tree.open(csv, false).unwrap();
+        read_all(&mut tree, "sales/country.txt");
+        assert_eq!(tree.rendered.size(csv), Some(SALES.len()));
+        tree.release(csv);
+        let country = tree.resolve(Path::new("sales/country.txt")).unwrap();
+        tree.rendered.remove(country);
+        read_all(&mut tree, "sales/country.txt");
+        assert_eq!(tree.rendered.size(csv), None);
+
+        let sales = tree.resolve(Path::new("sales")).unwrap();
+        let stat = tree.stat(sales).unwrap();
+        // `.`, the entry in the root, and `..` of `where`, `rows` and `row_groups`
+        assert_eq!((stat.perm, stat.nlink), (0o755, 5));
+        assert_eq!(tree.read(sales, 0, 10), Err(libc::EISDIR));
+    }
+
+    #[test]
+    fn test_colliding_column_files() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "t.parquet", "a/b,a_b\n1,2\n");
+        let mut tree = Tree::new(dir.path().to_path_buf());
+
+        let table = tree.resolve(Path::new("t")).unwrap();
+        let names = names(&tree, table);
+        assert!(names.contains(&"a_b.txt".to_string()), "{names:?}");
+        assert!(names.contains(&"a_b-1.txt".to_string()), "{names:?}");
+        assert_eq!(read_all(&mut tree, "t/a_b.txt"), "1\n");
+        assert_eq!(read_all(&mut tree, "t/a_b-1.txt"), "2\n");
+        assert!(tree.reported.contains(
+            "column 'a_b' of table 't' is shown as 'a_b-1.txt' because 'a_b.txt' is taken"
+        ));
+    }
+
+    #[test]
+    fn test_where_and_rows_are_created_on_lookup() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "sales.parquet", SALES);
+        let mut tree = Tree::new(dir.path().to_path_buf());
+
+        assert_eq!(
+            read_all(&mut tree, "sales/where/country=DE/id>1/data.csv"),
+            "id,country\n3,DE\n"
+        );
+        let filter = tree.resolve(Path::new("sales/where")).unwrap();
+        assert_eq!(names(&tree, filter), ["country=DE"]);
+        assert_eq!(
+            read_all(&mut tree, "sales/rows/1-1.jsonl"),
+            "{\"id\":2,\"country\":\"FR\"}\n"
+        );
+        assert_eq!(
+            tree.resolve(Path::new("sales/where/missing=1")),
+            Err(libc::ENOENT)
+        );
+        assert_eq!(
+            tree.resolve(Path::new("sales/where/id=abc")),
+            Err(libc::EINVAL)
+        );
+    }
+
+    #[test]
+    fn test_inodes_survive_rescans() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "sales.parquet", SALES);
+        let mut tree = Tree::new(dir.path().to_path_buf());
+        let csv = tree.resolve(Path::new("sales/data.csv")).unwrap();
+        let filtered = tree.resolve(Path::new("sales/where/country=FR")).unwrap();
+
+        write_table(dir.path(), "users.parquet", "id\n1\n");
+        tree.rescan();
+        assert_eq!(tree.resolve(Path::new("sales/data.csv")), Ok(csv));
+        assert_eq!(
+            tree.resolve(Path::new("sales/where/country=FR")),
+            Ok(filtered)
+        );
+        assert_eq!(names(&tree, ROOT_INO), ["sales", "users"]);
+
+        // A table that comes back gets its old inode numbers
+        std::fs::remove_file(dir.path().join("sales.parquet")).unwrap();
+        tree.rescan();
+        assert_eq!(tree.resolve(Path::new("sales")), Err(libc::ENOENT));
+        write_table(dir.path(), "sales.parquet", SALES);
+        tree.rescan();
+        assert_eq!(tree.resolve(Path::new("sales/data.csv")), Ok(csv));
+    }
+
+    #[test]
+    fn test_update_from_scan() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "sales.parquet", SALES);
+        let mut tree = Tree::new(dir.path().to_path_buf());
+        let scanner = tree.scanner();
+
+        // The tree stays usable while the scan runs
+        write_table(dir.path(), "users.parquet", "id\n1\n");
+        let scan = scanner.scan();
+        let filtered = tree.resolve(Path::new("sales/where/country=FR")).unwrap();
+        tree.update(scan);
+        assert_eq!(names(&tree, ROOT_INO), ["sales", "users"]);
+        assert_eq!(
+            tree.resolve(Path::new("sales/where/country=FR")),
+            Ok(filtered)
+        );
+    }
+
+    #[test]
+    fn test_inodes_survive_remounts_and_renames() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "sales.parquet", SALES);
+        let mut tree = Tree::new(dir.path().to_path_buf());
+        let table = tree.resolve(Path::new("sales")).unwrap();
+        let csv = tree.resolve(Path::new("sales/data.csv")).unwrap();
+
+        // Tables found before it no longer shift its inode numbers
+        write_table(dir.path(), "a.parquet", SALES);
+        let mut remounted = Tree::new(dir.path().to_path_buf());
+        assert_eq!(remounted.resolve(Path::new("sales/data.csv")), Ok(csv));
+
+        // Nor does a rename because of a new collision
+        let (schema, batches) = parse_text(SALES.as_bytes(), TextFormat::Csv).unwrap();
+        write_arrow(&dir.path().join("sales.arrow"), schema, &batches).unwrap();
+        let stale = tree.rescan();
+        assert_eq!(tree.resolve(Path::new("sales-parquet")), Ok(table));
+        assert_eq!(tree.resolve(Path::new("sales-parquet/data.csv")), Ok(csv));
+        assert!(stale.iter().any(|invalidation| matches!(
+            invalidation,
+            Invalidation::Entry { parent: ROOT_INO, name } if name == "sales"
+        )));
+    }
+
+    #[test]
+    fn test_forget_frees_removed_entries() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "sales.parquet", SALES);
+        let mut tree = Tree::new(dir.path().to_path_buf());
+        let csv = tree.resolve(Path::new("sales/data.csv")).unwrap();
+        tree.remember(csv);
+
+        std::fs::remove_file(dir.path().join("sales.parquet")).unwrap();
+        tree.rescan();
+        // The kernel still refers to the file, so its number stays taken
+        assert!(tree.inodes.key(csv).is_some());
+        assert_eq!(tree.stat(csv), Err(libc::ENOENT));
+        tree.forget(csv, 1);
+        assert!(tree.inodes.key(csv).is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "sales.parquet", SALES);
+        let mut tree = Tree::new(dir.path().to_path_buf());
+
+        assert_eq!(tree.resolve(Path::new("nope")), Err(libc::ENOENT));
+        assert_eq!(
+            tree.resolve(Path::new("sales/data.csv/x")),
+            Err(libc::ENOTDIR)
+        );
+        let csv = tree.resolve(Path::new("sales/data.csv")).unwrap();
+        assert_eq!(tree.list(csv), Err(libc::ENOTDIR));
+        assert_eq!(tree.open(csv, true), Err(libc::EACCES));
+        assert_eq!(tree.open(csv, false), Ok(()));
+        assert_eq!(tree.open(ROOT_INO, false), Err(libc::EISDIR));
+        assert_eq!(tree.create_upload(ROOT_INO, "new.csv"), Err(libc::EROFS));
+    }
+
+    #[test]
+    fn test_upload_becomes_table() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "sales.parquet", SALES);
+        let mut tree = Tree::new(dir.path().to_path_buf());
+        tree.set_read_write(true);
+
+        let upload = tree.create_upload(ROOT_INO, "users.csv").unwrap();
+        assert_eq!(tree.write(upload, 0, b"id,name\n1,ann\n"), Ok(14));
+        assert_eq!(tree.stat(upload).unwrap().perm, 0o644);
+        assert_eq!(tree.write(upload, MAX_UPLOAD_SIZE, b"x"), Err(libc::EFBIG));
+        assert_eq!(tree.write(upload, u64::MAX, b"x"), Err(libc::EFBIG));
+        assert_eq!(tree.truncate(upload, MAX_UPLOAD_SIZE + 1), Err(libc::EFBIG));
+        tree.flush(upload).unwrap();
+        assert_eq!(read_all(&mut tree, "users/name.txt"), "ann\n");
+
+        tree.release(upload);
+        assert_eq!(names(&tree, ROOT_INO), ["sales", "users"]);
+    }
+
+    fn sources(root: &Path, names: &[&str]) -> Vec<Source> {
+        let sources: Vec<Source> = names
+            .iter()
+            .map(|name| Source {
+                name: name.to_string(),
+                path: root.join(name),
+            })
+            .collect();
+        for source in &sources {
+            std::fs::create_dir_all(&source.path).unwrap();
+        }
+        sources
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "raw/sales.parquet", SALES);
+        let sources = sources(dir.path(), &["raw", "curated"]);
+        let mut tree = Tree::with_sources(sources, Layout::Namespaces);
+        tree.set_read_write(true);
+
+        // Namespaces are shown in the order of the sources, even without tables
+        assert_eq!(names(&tree, ROOT_INO), ["raw", "curated"]);
+        assert_eq!(read_all(&mut tree, "raw/sales/country.txt"), "DE\nFR\nDE\n");
+
+        assert_eq!(tree.create_upload(ROOT_INO, "users.csv"), Err(libc::EACCES));
+        let curated = tree.resolve(Path::new("curated")).unwrap();
+        let upload = tree.create_upload(curated, "users.csv").unwrap();
+        tree.write(upload, 0, b"id\n1\n").unwrap();
+        tree.flush(upload).unwrap();
+        assert!(dir.path().join("curated/users.parquet").exists());
+        assert_eq!(names(&tree, curated), ["users", "users.csv"]);
+    }
+
+    #[test]
+    fn test_overlay_merges_directories_and_resolves_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "raw/sales.parquet", SALES);
+        write_table(dir.path(), "raw/events/clicks.parquet", SALES);
+        write_table(dir.path(), "curated/sales.parquet", "id,country\n4,IT\n");
+        write_table(dir.path(), "curated/events/views.parquet", SALES);
+        let sources = sources(dir.path(), &["raw", "curated"]);
+
+        let overlay = Layout::Overlay(ConflictPolicy::First);
+        let mut tree = Tree::with_sources(sources.clone(), overlay);
+        assert_eq!(names(&tree, ROOT_INO), ["events", "sales"]);
+        let events = tree.resolve(Path::new("events")).unwrap();
+        assert_eq!(names(&tree, events), ["clicks", "views"]);
+        assert_eq!(read_all(&mut tree, "sales/country.txt"), "DE\nFR\nDE\n");
+        assert_eq!(tree.conflicts().len(), 1);
+
+        let overlay = Layout::Overlay(ConflictPolicy::Suffix);
+        let mut tree = Tree::with_sources(sources, overlay);
+        assert_eq!(
+            names(&tree, ROOT_INO),
+            ["events", "sales-raw", "sales-curated"]
+        );
+        assert_eq!(read_all(&mut tree, "sales-curated/country.txt"), "IT\n");
+        assert!(tree.conflicts().is_empty());
+    }
+
+    #[test]
+    fn test_declared_tables() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(
+            dir.path(),
+            "exports/sales-1.parquet",
+            "id,amt,note\n1,10,a\n",
+        );
+        write_table(
+            dir.path(),
+            "exports/sales-2.parquet",
+            "id,amt,note\n2,25,b\n",
+        );
+        write_table(dir.path(), "users.parquet", SALES);
+        let config: Config = r#"
+            [[table]]
+            name = "sales"
+            files = ["exports/sales-*.parquet"]
+            hide = ["note"]
+            rename = { amt = "amount" }
+            cast = { amt = "Float64" }
+        "#
+        .parse()
+        .unwrap();
+        let mut tree = Tree::new(dir.path().to_path_buf());
+        tree.set_read_write(true);
+        tree.set_config(config);
+
+        // The files of declared tables are not shown again by the scan
+        assert_eq!(names(&tree, ROOT_INO), ["sales", "users"]);
+        let sales = tree.resolve(Path::new("sales")).unwrap();
+        let files = names(&tree, sales);
+        assert!(files.contains(&"amount.txt".to_string()));
+        assert!(!files.contains(&"note.txt".to_string()));
+        assert!(!files.contains(&"append.csv".to_string()));
+        assert_eq!(
+            read_all(&mut tree, "sales/data.csv"),
+            "id,amount\n1,10.0\n2,25.0\n"
+        );
+        assert_eq!(read_all(&mut tree, "sales/where/amount>12.5/id.txt"), "2\n");
+        assert!(read_all(&mut tree, "sales/schema.json").contains("Float64"));
+    }
+
+    #[test]
+    fn test_declared_tables_of_overlaid_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        write_table(dir.path(), "raw/exports/sales-1.parquet", SALES);
+        write_table(dir.path(), "curated/exports/sales-2.parquet", SALES);
+        let sources = sources(dir.path(), &["raw", "curated"]);
+        let config: Config = r#"
+            [[table]]
+            name = "sales"
+            files = ["exports/sales-*.parquet"]
+
+            [[table]]
+            name = "empty"
+            files = ["*.csv"]
+        "#
+        .parse()
+        .unwrap();
+        let mut tree = Tree::with_sources(sources, Layout::Overlay(ConflictPolicy::First));
+        tree.set_config(config);
+
+        // Declared tables are built once from the files of every source
+        assert_eq!(names(&tree, ROOT_INO), ["sales"]);
+        assert!(tree.conflicts().is_empty());
+        assert_eq!(tree.reported.len(), 1);
+        assert_eq!(read_all(&mut tree, "sales/id.txt"), "1\n2\n3\n1\n2\n3\n");
+
+        // They have no file of their own and take the metadata of their newest part
+        let part = dir.path().join("curated/exports/sales-2.parquet");
+        let newest = SourceMeta::of(&part);
+        let sales = tree.resolve(Path::new("sales")).unwrap();
+        let stat = tree.stat(sales).unwrap();
+        assert_eq!((stat.uid, stat.mtime), (newest.uid, newest.mtime));
+        assert_ne!(stat.mtime, UNIX_EPOCH);
+    }
+}
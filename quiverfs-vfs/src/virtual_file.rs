@@ -0,0 +1,82 @@
+use quiverfs_core::parquet_metadata::parquet_row_group_to_json;
+use quiverfs_core::render::{
+    column_to_text, table_to_arrow, table_to_csv, table_to_jsonl, table_to_parquet,
+};
+use quiverfs_core::stats::table_stats;
+use quiverfs_core::{Dataset, Selection};
+
+/// A read-only file whose contents are generated from a table's source data.
+#[derive(Debug, Clone)]
+pub enum VirtualFile {
+    /// A single column rendered as newline-delimited values.
+    Column { index: usize },
+    /// The table schema as pretty-printed JSON.
+    Schema,
+    /// The row count and per-column statistics of the table as pretty-printed JSON.
+    Stats,
+    /// The whole table as CSV with a header row.
+    Csv,
+    /// The whole table as newline-delimited JSON objects.
+    Jsonl,
+    /// The table re-encoded as a single Parquet file.
+    Parquet,
+    /// The table re-encoded as a single Arrow IPC file.
+    Arrow,
+    /// The footer metadata of a Parquet row group, numbered across all parts, as
+    /// pretty-printed JSON.
+    RowGroupMetadata { index: usize },
+}
+
+impl VirtualFile {
+    /// Generates the full contents of this file from the rows of `dataset` picked by
+    /// `selection`.
+    pub fn render(&self, dataset: &Dataset, selection: &Selection) -> Result<Vec<u8>, String> {
+        match self {
+            VirtualFile::Column { index } => {
+                let (_, batches) = dataset.read(selection)?;
+                column_to_text(&batches, *index)
+            }
+            VirtualFile::Schema => {
+                let json = dataset.schema_json()?;
+                let mut data = serde_json::to_vec_pretty(&json).map_err(|e| e.to_string())?;
+                data.push(b'\n');
+                Ok(data)
+            }
+            VirtualFile::Stats => {
+                let json = table_stats(dataset)?;
+                let mut data = serde_json::to_vec_pretty(&json).map_err(|e| e.to_string())?;
+                data.push(b'\n');
+                Ok(data)
+            }
+            VirtualFile::Csv => {
+                let (schema, batches) = dataset.read(selection)?;
+                table_to_csv(&schema, &batches)
+            }
+            VirtualFile::Jsonl => {
+                let (_, batches) = dataset.read(selection)?;
+                table_to_jsonl(&batches)
+            }
+            VirtualFile::Parquet => {
+                let (schema, batches) = dataset.read(selection)?;
+                table_to_parquet(schema, &batches)
+            }
+            VirtualFile::Arrow => {
+                let (schema, batches) = dataset.read(selection)?;
+                table_to_arrow(schema, &batches)
+            }
+            VirtualFile::RowGroupMetadata { index } => {
+                let row_groups = dataset.row_groups()?;
+                let Some((part, index)) = row_groups.get(*index) else {
+                    return Err(format!(
+                        "Dataset '{}' has no row group {}",
+                        dataset.name, index
+                    ));
+                };
+                let json = parquet_row_group_to_json(part, *index)?;
+                let mut data = serde_json::to_vec_pretty(&json).map_err(|e| e.to_string())?;
+                data.push(b'\n');
+                Ok(data)
+            }
+        }
+    }
+}